@@ -303,6 +303,12 @@ impl Tipset {
         self.key
             .get_or_init(|| TipsetKey::from(self.headers.iter_ne().map(|h| *h.cid()).collect_vec()))
     }
+    /// Returns a single CID standing for the tipset key, see
+    /// [`TipsetKey::cid`]. Blocks are ordered by ticket, so the CID doesn't
+    /// depend on the order the blocks were given in.
+    pub fn key_cid(&self) -> anyhow::Result<Cid> {
+        self.key().cid()
+    }
     /// Returns a non-empty collection of `CIDs` for the current tipset
     pub fn cids(&self) -> NonEmpty<Cid> {
         self.key().to_cids()
@@ -797,6 +803,22 @@ mod test {
         );
     }
 
+    #[test]
+    fn key_cid_ignores_block_order() {
+        let (b1, b2, b3) = (
+            mock_block(1234561, 1, 1),
+            mock_block(1234562, 1, 2),
+            mock_block(1234563, 1, 3),
+        );
+        let ts1 = Tipset::new(vec![b1.clone(), b2.clone(), b3.clone()]).unwrap();
+        let ts2 = Tipset::new(vec![b3.clone(), b1.clone(), b2.clone()]).unwrap();
+        assert_eq!(ts1.key_cid().unwrap(), ts2.key_cid().unwrap());
+        assert_eq!(ts1.key_cid().unwrap(), ts1.key().cid().unwrap());
+
+        let ts3 = Tipset::new(vec![b1, b2]).unwrap();
+        assert_ne!(ts1.key_cid().unwrap(), ts3.key_cid().unwrap());
+    }
+
     #[test]
     fn ensure_there_are_blocks() {
         assert_eq!(
@@ -0,0 +1,205 @@
+// Copyright 2019-2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use crate::blocks::{Tipset, TipsetKey};
+use crate::chain::ChainStore;
+use crate::shim::clock::ChainEpoch;
+use fvm_ipld_blockstore::Blockstore;
+use std::sync::Arc;
+
+use super::Weight;
+
+/// A chain invariant that doesn't hold for a tipset.
+#[derive(Debug, Clone, PartialEq, Eq, displaydoc::Display)]
+pub enum ChainInvariantViolation {
+    /// tipset {key} at epoch {epoch} doesn't come after its parent at epoch {parent_epoch}
+    EpochNotIncreasing {
+        key: TipsetKey,
+        epoch: ChainEpoch,
+        parent_epoch: ChainEpoch,
+    },
+    /// tipset {key} at epoch {epoch} has timestamp {timestamp}, expected {expected}
+    WrongTimestamp {
+        key: TipsetKey,
+        epoch: ChainEpoch,
+        timestamp: u64,
+        expected: u64,
+    },
+    /// tipset {key} has parent weight {weight}, not above its parent's {parent_weight}
+    WeightNotIncreasing {
+        key: TipsetKey,
+        weight: Weight,
+        parent_weight: Weight,
+    },
+    /// parents {parents} of tipset {key} can't be loaded
+    MissingParent { key: TipsetKey, parents: TipsetKey },
+    /// tipset {from} is not an ancestor of tipset {to}
+    NotAncestor { from: TipsetKey, to: TipsetKey },
+}
+
+/// Walks back from `to` to its ancestor `from` and returns all the violations
+/// of the basic chain invariants found on the way:
+/// - epochs strictly increase from parent to child,
+/// - block timestamps are `genesis + epoch * block_delay`,
+/// - parent weights strictly increase from parent to child,
+/// - every parent tipset can be loaded.
+///
+/// Walking stops at the first missing parent.
+pub fn check_chain_invariants<DB: Blockstore>(
+    cs: &ChainStore<DB>,
+    from: &Tipset,
+    to: Arc<Tipset>,
+) -> anyhow::Result<Vec<ChainInvariantViolation>> {
+    use ChainInvariantViolation::*;
+
+    let genesis_timestamp = cs.genesis_block_header().timestamp;
+    let block_delay = cs.chain_config().block_delay_secs as u64;
+    let mut violations = vec![];
+    let to_key = to.key().clone();
+    let mut current = to;
+    loop {
+        let expected = genesis_timestamp + current.epoch() as u64 * block_delay;
+        for header in current.block_headers() {
+            if header.timestamp != expected {
+                violations.push(WrongTimestamp {
+                    key: current.key().clone(),
+                    epoch: current.epoch(),
+                    timestamp: header.timestamp,
+                    expected,
+                });
+            }
+        }
+        if current.epoch() <= from.epoch() {
+            break;
+        }
+        let Some(parent) = cs.chain_index.load_tipset(current.parents())? else {
+            violations.push(MissingParent {
+                key: current.key().clone(),
+                parents: current.parents().clone(),
+            });
+            return Ok(violations);
+        };
+        if parent.epoch() >= current.epoch() {
+            violations.push(EpochNotIncreasing {
+                key: current.key().clone(),
+                epoch: current.epoch(),
+                parent_epoch: parent.epoch(),
+            });
+        }
+        if current.weight() <= parent.weight() {
+            violations.push(WeightNotIncreasing {
+                key: current.key().clone(),
+                weight: current.weight().clone(),
+                parent_weight: parent.weight().clone(),
+            });
+        }
+        current = parent;
+    }
+    if current.key() != from.key() {
+        violations.push(NotAncestor {
+            from: from.key().clone(),
+            to: to_key,
+        });
+    }
+    Ok(violations)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{CachingBlockHeader, RawBlockHeader};
+    use crate::db::MemoryDB;
+    use crate::networks::ChainConfig;
+    use crate::shim::address::Address;
+    use crate::utils::db::CborStoreExt as _;
+
+    #[test]
+    fn check_chain_invariants_flags_broken_chain() {
+        let db = Arc::new(MemoryDB::default());
+        let chain_config = Arc::new(ChainConfig::default());
+        let block_delay = chain_config.block_delay_secs as u64;
+        let tipset_at = |epoch: ChainEpoch, parents: TipsetKey, weight: u64, timestamp: u64| {
+            let header = CachingBlockHeader::new(RawBlockHeader {
+                miner_address: Address::new_id(0),
+                epoch,
+                parents,
+                weight: weight.into(),
+                timestamp,
+                ..Default::default()
+            });
+            db.put_cbor_default(&header).unwrap();
+            Arc::new(Tipset::from(header))
+        };
+        let tipset = |epoch: ChainEpoch, parents: TipsetKey, weight: u64| {
+            tipset_at(epoch, parents, weight, epoch as u64 * block_delay)
+        };
+
+        let genesis = tipset(0, RawBlockHeader::default().parents, 0);
+        let cs = ChainStore::new(
+            db.clone(),
+            db.clone(),
+            db.clone(),
+            chain_config,
+            genesis.block_headers().first().clone(),
+        )
+        .unwrap();
+
+        let ts1 = tipset(1, genesis.key().clone(), 10);
+        let ts3 = tipset(3, ts1.key().clone(), 20);
+        assert_eq!(
+            check_chain_invariants(&cs, &genesis, ts3.clone()).unwrap(),
+            vec![]
+        );
+
+        // Same weight as the parent
+        let ts4 = tipset(4, ts3.key().clone(), 20);
+        assert_eq!(
+            check_chain_invariants(&cs, &genesis, ts4.clone()).unwrap(),
+            vec![ChainInvariantViolation::WeightNotIncreasing {
+                key: ts4.key().clone(),
+                weight: 20.into(),
+                parent_weight: 20.into(),
+            }]
+        );
+
+        // Same epoch as the parent
+        let repeated = tipset(3, ts3.key().clone(), 30);
+        assert_eq!(
+            check_chain_invariants(&cs, &genesis, repeated.clone()).unwrap(),
+            vec![ChainInvariantViolation::EpochNotIncreasing {
+                key: repeated.key().clone(),
+                epoch: 3,
+                parent_epoch: 3,
+            }]
+        );
+
+        // Timestamp off the epoch schedule
+        let late = tipset_at(4, ts3.key().clone(), 30, 4 * block_delay + 1);
+        assert_eq!(
+            check_chain_invariants(&cs, &genesis, late.clone()).unwrap(),
+            vec![ChainInvariantViolation::WrongTimestamp {
+                key: late.key().clone(),
+                epoch: 4,
+                timestamp: 4 * block_delay + 1,
+                expected: 4 * block_delay,
+            }]
+        );
+
+        // Start of the walk on another fork
+        let fork = tipset(2, ts1.key().clone(), 15);
+        assert_eq!(
+            check_chain_invariants(&cs, &fork, ts3.clone()).unwrap(),
+            vec![ChainInvariantViolation::NotAncestor {
+                from: fork.key().clone(),
+                to: ts3.key().clone(),
+            }]
+        );
+
+        // Unknown parent
+        let orphan = tipset(5, RawBlockHeader::default().parents, 30);
+        assert!(matches!(
+            check_chain_invariants(&cs, &genesis, orphan).unwrap()[..],
+            [ChainInvariantViolation::MissingParent { .. }]
+        ));
+    }
+}
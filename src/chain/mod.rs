@@ -1,5 +1,6 @@
 // Copyright 2019-2024 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
+mod invariants;
 pub mod store;
 mod weight;
 use crate::blocks::Tipset;
@@ -14,7 +15,7 @@ use fvm_ipld_blockstore::Blockstore;
 use std::sync::Arc;
 use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
 
-pub use self::{store::*, weight::*};
+pub use self::{invariants::*, store::*, weight::*};
 
 pub async fn export<D: Digest>(
     db: Arc<impl Blockstore + Send + Sync + 'static>,
@@ -56,10 +56,13 @@ fn compute_next_base_fee(
     next_base_fee
 }
 
+/// Computes the base fee of the tipset following `ts`. If `min_base_fee` is
+/// set, the result is clamped so that it never drops below it.
 pub fn compute_base_fee<DB>(
     db: &DB,
     ts: &Tipset,
     smoke_height: ChainEpoch,
+    min_base_fee: Option<&TokenAmount>,
 ) -> Result<TokenAmount, crate::chain::Error>
 where
     DB: Blockstore,
@@ -88,19 +91,24 @@ where
 
     // Compute next base fee based on the current gas limit and parent base fee.
     let parent_base_fee = &ts.block_headers().first().parent_base_fee;
-    Ok(compute_next_base_fee(
+    let next_base_fee = compute_next_base_fee(
         parent_base_fee,
         total_limit,
         ts.block_headers().len(),
         ts.epoch(),
         smoke_height,
-    ))
+    );
+    match min_base_fee {
+        Some(min_base_fee) if &next_base_fee < min_base_fee => Ok(min_base_fee.clone()),
+        _ => Ok(next_base_fee),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::blocks::RawBlockHeader;
     use crate::blocks::{CachingBlockHeader, Tipset};
+    use crate::chain_sync::TipsetValidator;
     use crate::db::MemoryDB;
     use crate::networks::{ChainConfig, Height};
     use crate::shim::address::Address;
@@ -174,6 +182,32 @@ mod tests {
         });
         let ts = Tipset::from(h0);
         let smoke_height = ChainConfig::default().epoch(Height::Smoke);
-        assert!(compute_base_fee(&blockstore, &ts, smoke_height).is_err());
+        assert!(compute_base_fee(&blockstore, &ts, smoke_height, None).is_err());
+    }
+
+    #[test]
+    fn compute_base_fee_is_clamped_to_floor() {
+        let blockstore = MemoryDB::default();
+        let messages = TipsetValidator::compute_msg_root(&blockstore, &[], &[]).unwrap();
+        let h0 = CachingBlockHeader::new(RawBlockHeader {
+            miner_address: Address::new_id(0),
+            messages,
+            parent_base_fee: TokenAmount::from_atto(MINIMUM_BASE_FEE),
+            ..Default::default()
+        });
+        let ts = Tipset::from(h0);
+        let smoke_height = ChainConfig::default().epoch(Height::Smoke);
+
+        // Without a floor, an empty tipset keeps the base fee at the protocol minimum.
+        assert_eq!(
+            compute_base_fee(&blockstore, &ts, smoke_height, None).unwrap(),
+            TokenAmount::from_atto(MINIMUM_BASE_FEE)
+        );
+
+        let floor = TokenAmount::from_atto(1_000_000);
+        assert_eq!(
+            compute_base_fee(&blockstore, &ts, smoke_height, Some(&floor)).unwrap(),
+            floor
+        );
     }
 }
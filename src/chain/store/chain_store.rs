@@ -8,16 +8,13 @@ use crate::fil_cns;
 use crate::interpreter::BlockMessages;
 use crate::interpreter::VMTrace;
 use crate::libp2p_bitswap::{BitswapStoreRead, BitswapStoreReadWrite};
-use crate::message::{ChainMessage, Message as MessageTrait, SignedMessage};
+use crate::message::{ChainMessage, SignedMessage};
 use crate::networks::{ChainConfig, Height};
 use crate::rpc::eth::{self, eth_tx_from_signed_eth_message};
 use crate::shim::clock::ChainEpoch;
-use crate::shim::{
-    address::Address, econ::TokenAmount, executor::Receipt, message::Message,
-    state_tree::StateTree, version::NetworkVersion,
-};
+use crate::shim::{executor::Receipt, message::Message, version::NetworkVersion};
 use crate::utils::db::{BlockstoreExt, CborStoreExt};
-use ahash::{HashMap, HashMapExt, HashSet};
+use ahash::{HashMap, HashSet};
 use anyhow::Context;
 use cid::Cid;
 use fil_actors_shared::fvm_ipld_amt::Amtv0 as Amt;
@@ -161,6 +158,12 @@ where
         self.tipset_tracker.add(header);
     }
 
+    /// Returns the tracked headers at the height of `header` that were
+    /// produced by the same miner, i.e. the blocks it equivocates with.
+    pub fn equivocating_blocks(&self, header: &CachingBlockHeader) -> Vec<Cid> {
+        self.tipset_tracker.blocks_from_same_miner(header)
+    }
+
     /// Writes tipset block headers to data store and updates heaviest tipset
     /// with other compatible tracked headers.
     pub fn put_tipset(&self, ts: &Tipset) -> Result<(), Error> {
@@ -229,6 +232,10 @@ where
         &self.genesis_block_header
     }
 
+    pub fn chain_config(&self) -> &Arc<ChainConfig> {
+        &self.chain_config
+    }
+
     /// Returns the currently tracked heaviest tipset.
     pub fn heaviest_tipset(&self) -> Arc<Tipset> {
         self.chain_index
@@ -246,6 +253,32 @@ where
         &self.publisher
     }
 
+    /// Walks back from `from` and `to` to their common ancestor. Returns the
+    /// tipsets to revert, newest first, and the tipsets to apply, oldest
+    /// first, in order to move the head from `from` to `to`.
+    #[allow(clippy::type_complexity)]
+    pub fn reorg_ops(
+        &self,
+        from: Arc<Tipset>,
+        to: Arc<Tipset>,
+    ) -> Result<(Vec<Arc<Tipset>>, Vec<Arc<Tipset>>), Error> {
+        let mut left = from;
+        let mut right = to;
+        let mut reverts = Vec::new();
+        let mut applies = Vec::new();
+        while left != right {
+            if left.epoch() > right.epoch() {
+                let parent = self.chain_index.load_required_tipset(left.parents())?;
+                reverts.push(std::mem::replace(&mut left, parent));
+            } else {
+                let parent = self.chain_index.load_required_tipset(right.parents())?;
+                applies.push(std::mem::replace(&mut right, parent));
+            }
+        }
+        applies.reverse();
+        Ok((reverts, applies))
+    }
+
     /// Returns key-value store instance.
     pub fn blockstore(&self) -> &DB {
         &self.db
@@ -558,64 +591,6 @@ where
         .ok_or_else(|| Error::UndefinedKey(key.to_string()))
 }
 
-/// Given a tipset this function will return all unique messages in that tipset.
-pub fn messages_for_tipset<DB>(db: Arc<DB>, ts: &Tipset) -> Result<Vec<ChainMessage>, Error>
-where
-    DB: Blockstore,
-{
-    let mut applied: HashMap<Address, u64> = HashMap::new();
-    let mut balances: HashMap<Address, TokenAmount> = HashMap::new();
-    let state = StateTree::new_from_tipset(Arc::clone(&db), ts)?;
-
-    // message to get all messages for block_header into a single iterator
-    let mut get_message_for_block_header =
-        |b: &CachingBlockHeader| -> Result<Vec<ChainMessage>, Error> {
-            let (unsigned, signed) = block_messages(&db, b)?;
-            let mut messages = Vec::with_capacity(unsigned.len() + signed.len());
-            let unsigned_box = unsigned.into_iter().map(ChainMessage::Unsigned);
-            let signed_box = signed.into_iter().map(ChainMessage::Signed);
-
-            for message in unsigned_box.chain(signed_box) {
-                let from_address = &message.from();
-                if applied.contains_key(from_address) {
-                    let actor_state = state
-                        .get_actor(from_address)?
-                        .ok_or_else(|| Error::Other("Actor state not found".to_string()))?;
-                    applied.insert(*from_address, actor_state.sequence);
-                    balances.insert(*from_address, actor_state.balance.clone().into());
-                }
-                if let Some(seq) = applied.get_mut(from_address) {
-                    if *seq != message.sequence() {
-                        continue;
-                    }
-                    *seq += 1;
-                } else {
-                    continue;
-                }
-                if let Some(bal) = balances.get_mut(from_address) {
-                    if *bal < message.required_funds() {
-                        continue;
-                    }
-                    *bal -= message.required_funds();
-                } else {
-                    continue;
-                }
-
-                messages.push(message)
-            }
-
-            Ok(messages)
-        };
-
-    ts.block_headers()
-        .iter()
-        .try_fold(Vec::new(), |mut message_vec, b| {
-            let mut messages = get_message_for_block_header(b)?;
-            message_vec.append(&mut messages);
-            Ok(message_vec)
-        })
-}
-
 /// Returns messages from key-value store based on a slice of [`Cid`]s.
 pub fn messages_from_cids<DB, T>(db: &DB, keys: &[Cid]) -> Result<Vec<T>, Error>
 where
@@ -716,4 +691,44 @@ mod tests {
         cs.mark_block_as_validated(&cid);
         assert!(cs.is_block_validated(&cid));
     }
+
+    #[test]
+    fn reorg_ops_test() {
+        let db = Arc::new(crate::db::MemoryDB::default());
+        let chain_config = Arc::new(ChainConfig::default());
+        let tipset = |epoch, parents: TipsetKey, timestamp| {
+            let header = CachingBlockHeader::new(RawBlockHeader {
+                miner_address: Address::new_id(0),
+                epoch,
+                parents,
+                timestamp,
+                ..Default::default()
+            });
+            db.put_cbor_default(&header).unwrap();
+            Arc::new(Tipset::from(header))
+        };
+
+        let genesis = tipset(0, RawBlockHeader::default().parents, 0);
+        let cs = ChainStore::new(
+            db.clone(),
+            db.clone(),
+            db.clone(),
+            chain_config,
+            genesis.block_headers().first().clone(),
+        )
+        .unwrap();
+
+        let a1 = tipset(1, genesis.key().clone(), 1);
+        let a2 = tipset(2, a1.key().clone(), 2);
+        let b1 = tipset(1, genesis.key().clone(), 3);
+
+        assert_eq!(
+            cs.reorg_ops(a2.clone(), b1.clone()).unwrap(),
+            (vec![a2.clone(), a1.clone()], vec![b1])
+        );
+        assert_eq!(
+            cs.reorg_ops(genesis, a2.clone()).unwrap(),
+            (vec![], vec![a1, a2])
+        );
+    }
 }
@@ -11,7 +11,7 @@ use cid::Cid;
 use fvm_ipld_blockstore::Blockstore;
 use nunny::vec as nonempty;
 use parking_lot::Mutex;
-use tracing::{debug, warn};
+use tracing::debug;
 
 /// Tracks blocks by their height for the purpose of forming tipsets.
 #[derive(Default)]
@@ -38,34 +38,34 @@ impl<DB: Blockstore> TipsetTracker<DB> {
             debug!("tried to add block to tipset tracker that was already there");
             return;
         }
-        let cids_to_verify = cids.to_owned();
         cids.push(*header.cid());
         drop(map_lock);
 
-        self.check_multiple_blocks_from_same_miner(&cids_to_verify, header);
         self.prune_entries(header.epoch);
     }
 
-    /// Checks if there are multiple blocks from the same miner at the same
-    /// height.
+    /// Returns the tracked blocks that were produced by the miner of `header`
+    /// at the same height, excluding `header` itself.
     ///
     /// This should never happen. Something is weird as it's against the
     /// protocol rules for a miner to produce multiple blocks at the same
     /// height.
-    fn check_multiple_blocks_from_same_miner(&self, cids: &[Cid], header: &CachingBlockHeader) {
-        for cid in cids.iter() {
-            if let Ok(Some(block)) = CachingBlockHeader::load(&self.db, *cid) {
-                if header.miner_address == block.miner_address {
-                    warn!(
-                        "Have multiple blocks from miner {} at height {} in our tipset cache {}-{}",
-                        header.miner_address,
-                        header.epoch,
-                        header.cid(),
-                        cid
-                    );
-                }
-            }
-        }
+    pub fn blocks_from_same_miner(&self, header: &CachingBlockHeader) -> Vec<Cid> {
+        let cids = self
+            .entries
+            .lock()
+            .get(&header.epoch)
+            .cloned()
+            .unwrap_or_default();
+        cids.into_iter()
+            .filter(|cid| cid != header.cid())
+            .filter(|cid| {
+                matches!(
+                    CachingBlockHeader::load(&self.db, *cid),
+                    Ok(Some(block)) if block.miner_address == header.miner_address
+                )
+            })
+            .collect()
     }
 
     /// Deletes old entries in the `TipsetTracker` that are past the chain
@@ -156,4 +156,39 @@ mod test {
             ]
         );
     }
+
+    #[test]
+    fn detects_blocks_from_same_miner_at_same_epoch() {
+        use crate::blocks::RawBlockHeader;
+        use crate::shim::address::Address;
+        use crate::utils::db::CborStoreExt as _;
+
+        let db = Arc::new(MemoryDB::default());
+        let tipset_tracker = TipsetTracker::new(db.clone(), Arc::new(ChainConfig::default()));
+        let header = |miner, timestamp| {
+            let header = CachingBlockHeader::new(RawBlockHeader {
+                miner_address: Address::new_id(miner),
+                epoch: 10,
+                timestamp,
+                ..Default::default()
+            });
+            db.put_cbor_default(&header).unwrap();
+            header
+        };
+
+        let first = header(1000, 1);
+        tipset_tracker.add(&first);
+        let other_miner = header(1001, 1);
+        assert!(tipset_tracker
+            .blocks_from_same_miner(&other_miner)
+            .is_empty());
+        tipset_tracker.add(&other_miner);
+        assert!(tipset_tracker.blocks_from_same_miner(&first).is_empty());
+
+        let conflicting = header(1000, 2);
+        assert_eq!(
+            tipset_tracker.blocks_from_same_miner(&conflicting),
+            vec![*first.cid()]
+        );
+    }
 }
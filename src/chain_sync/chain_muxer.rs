@@ -24,7 +24,10 @@ use crate::libp2p::{
 };
 use crate::message::SignedMessage;
 use crate::message_pool::{MessagePool, Provider};
-use crate::shim::{clock::SECONDS_IN_DAY, message::Message};
+use crate::shim::{
+    clock::{ALLOWABLE_CLOCK_DRIFT, SECONDS_IN_DAY},
+    message::Message,
+};
 use crate::state_manager::StateManager;
 use crate::{
     blocks::{Block, CreateTipsetError, FullTipset, GossipBlock, Tipset, TipsetKey},
@@ -48,6 +51,7 @@ use tracing::{debug, error, info, trace, warn};
 const DEFAULT_REQUEST_WINDOW: usize = 8;
 const DEFAULT_TIPSET_SAMPLE_SIZE: usize = 1;
 const DEFAULT_RECENT_STATE_ROOTS: i64 = 2000;
+const DEFAULT_MAX_PENDING_TIPSETS: usize = 20;
 
 pub(in crate::chain_sync) type WorkerState = Arc<RwLock<SyncState>>;
 
@@ -78,7 +82,7 @@ pub enum ChainMuxerError {
 }
 
 /// Structure that defines syncing configuration options
-#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
 #[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
 pub struct SyncConfig {
     /// Request window length for tipsets during chain exchange
@@ -91,6 +95,66 @@ pub struct SyncConfig {
     /// head is
     #[cfg_attr(test, arbitrary(gen(|g| u32::arbitrary(g) as _)))]
     pub tipset_sample_size: usize,
+    /// Reject, and mark as bad, valid blocks from a miner that already
+    /// produced a different block at the same epoch. Equivocations are
+    /// always reported, this only controls whether the block is accepted.
+    #[serde(default)]
+    pub reject_equivocating_blocks: bool,
+    /// Maximum size in bytes of a serialized block header. Larger blocks are
+    /// rejected by validation and not created by `Filecoin.MinerCreateBlock`.
+    /// Unlimited when unset.
+    #[serde(default)]
+    #[cfg_attr(test, arbitrary(gen(|g| Option::<u32>::arbitrary(g).map(|it| it as _))))]
+    pub max_block_header_size: Option<usize>,
+    /// Maximum number of gossiped tipsets waiting for validation. When the
+    /// queue is full, duplicates and the lowest epoch tipsets are dropped.
+    /// Defaults to 20 when unset.
+    #[serde(default)]
+    #[cfg_attr(test, arbitrary(gen(|g| Option::<u32>::arbitrary(g).map(|it| it as _))))]
+    pub max_pending_tipsets: Option<usize>,
+    /// Store the events emitted by messages when computing tipset state, so
+    /// that they can be looked up by the events root of their receipts. Off
+    /// by default, as it grows the database on every tipset.
+    #[serde(default)]
+    pub persist_events: bool,
+    /// Fraction of tipsets, between 0 and 1, whose execution is traced when
+    /// their state is computed. The traces of the most recent samples are
+    /// kept in memory for `Forest.StateTraceSamples`. Off by default.
+    #[serde(default)]
+    #[cfg_attr(test, arbitrary(gen(|g| f64::from(u8::arbitrary(g)) / 255.0)))]
+    pub trace_sample_rate: f64,
+    /// Maximum time in milliseconds `Filecoin.MinerCreateBlock` may spend
+    /// building a block. A block over budget is not created, so the miner
+    /// skips the round instead of falling behind. Unlimited when unset.
+    #[serde(default)]
+    #[cfg_attr(test, arbitrary(gen(|g| Option::<u32>::arbitrary(g).map(|it| it as _))))]
+    pub max_block_build_time_ms: Option<u64>,
+    /// Number of threads of a pool dedicated to computing tipset state, which
+    /// keeps VM execution from competing with other node tasks. State is
+    /// computed on the shared blocking thread pool when unset.
+    #[serde(default)]
+    #[cfg_attr(test, arbitrary(gen(|g| Option::<u8>::arbitrary(g).map(|it| it as _))))]
+    pub state_compute_threads: Option<usize>,
+    /// Number of seconds a block timestamp may be ahead of the node clock.
+    /// Blocks further ahead are rejected by validation and not created by
+    /// `Filecoin.MinerCreateBlock`. Defaults to `ALLOWABLE_CLOCK_DRIFT` when
+    /// unset.
+    #[serde(default)]
+    #[cfg_attr(test, arbitrary(gen(|g| Option::<u32>::arbitrary(g).map(|it| it as _))))]
+    pub allowable_clock_drift_secs: Option<u64>,
+}
+
+impl SyncConfig {
+    pub fn pending_tipsets_capacity(&self) -> usize {
+        self.max_pending_tipsets
+            .unwrap_or(DEFAULT_MAX_PENDING_TIPSETS)
+            .max(1)
+    }
+
+    pub fn allowable_clock_drift(&self) -> u64 {
+        self.allowable_clock_drift_secs
+            .unwrap_or(ALLOWABLE_CLOCK_DRIFT)
+    }
 }
 
 impl Default for SyncConfig {
@@ -99,8 +163,74 @@ impl Default for SyncConfig {
             request_window: DEFAULT_REQUEST_WINDOW,
             recent_state_roots: DEFAULT_RECENT_STATE_ROOTS,
             tipset_sample_size: DEFAULT_TIPSET_SAMPLE_SIZE,
+            reject_equivocating_blocks: false,
+            max_block_header_size: None,
+            max_pending_tipsets: None,
+            persist_events: false,
+            trace_sample_rate: 0.0,
+            max_block_build_time_ms: None,
+            state_compute_threads: None,
+            allowable_clock_drift_secs: None,
+        }
+    }
+}
+
+/// Queues `tipset` for the tipset processor. When the queue is full, the
+/// pending tipsets are deduplicated and the lowest epochs are dropped to make
+/// room, so that a burst of gossiped blocks can't stall the network stream.
+fn enqueue_tipset(
+    sender: &flume::Sender<Arc<Tipset>>,
+    receiver: &flume::Receiver<Arc<Tipset>>,
+    tipset: Arc<Tipset>,
+) -> Result<(), ChainMuxerError> {
+    let tipset = match sender.try_send(tipset) {
+        Ok(()) => return Ok(()),
+        Err(flume::TrySendError::Full(tipset)) => tipset,
+        Err(why) => {
+            debug!("Sending tipset to TipsetProcessor failed: {}", why);
+            return Err(ChainMuxerError::TipsetChannelSend(why.to_string()));
+        }
+    };
+
+    let mut pending = receiver.drain().collect_vec();
+    pending.push(tipset);
+    pending.sort_by(|a, b| {
+        b.epoch()
+            .cmp(&a.epoch())
+            .then_with(|| b.weight().cmp(a.weight()))
+    });
+    let mut seen = ahash::HashSet::default();
+    pending.retain(|ts| seen.insert(ts.key().clone()));
+
+    let capacity = sender.capacity().unwrap_or(usize::MAX);
+    if pending.len() > capacity {
+        warn!(
+            "Tipset queue is full, dropping {} pending tipsets",
+            pending.len() - capacity
+        );
+        pending.truncate(capacity);
+    }
+    // Other senders (e.g. `SyncSubmitBlock`) may take the freed slots while the
+    // queue is refilled. Evict the oldest, lowest-epoch entries to make room
+    // rather than failing, which would lose the drained tipsets.
+    for mut ts in pending.into_iter().rev() {
+        loop {
+            match sender.try_send(ts) {
+                Ok(()) => break,
+                Err(flume::TrySendError::Full(rejected)) => {
+                    ts = rejected;
+                    if let Ok(dropped) = receiver.try_recv() {
+                        warn!(
+                            "Tipset queue is full, dropping tipset at epoch {}",
+                            dropped.epoch()
+                        );
+                    }
+                }
+                Err(why) => return Err(ChainMuxerError::TipsetChannelSend(why.to_string())),
+            }
         }
     }
+    Ok(())
 }
 
 /// Represents the result of evaluating the network head tipset against the
@@ -837,19 +967,18 @@ where
         let bad_block_cache = self.bad_blocks.clone();
         let mem_pool = self.mpool.clone();
         let tipset_sender = self.tipset_sender.clone();
+        let tipset_receiver = self.tipset_receiver.clone();
         let block_delay = self.state_manager.chain_config().block_delay_secs;
         let stateless_mode = self.stateless_mode;
         let stream_processor: ChainMuxerFuture<UnexpectedReturnKind, ChainMuxerError> = Box::pin(
             async move {
                 // If a tipset has been provided, pass it to the tipset processor
                 if let Some(tipset) = tipset_opt {
-                    if let Err(why) = tipset_sender
-                        .send_async(Arc::new(tipset.into_tipset()))
-                        .await
-                    {
-                        debug!("Sending tipset to TipsetProcessor failed: {}", why);
-                        return Err(ChainMuxerError::TipsetChannelSend(why.to_string()));
-                    };
+                    enqueue_tipset(
+                        &tipset_sender,
+                        &tipset_receiver,
+                        Arc::new(tipset.into_tipset()),
+                    )?;
                 }
                 loop {
                     let event = match p2p_messages.recv_async().await {
@@ -889,13 +1018,11 @@ where
                         continue;
                     }
 
-                    if let Err(why) = tipset_sender
-                        .send_async(Arc::new(tipset.into_tipset()))
-                        .await
-                    {
-                        debug!("Sending tipset to TipsetProcessor failed: {}", why);
-                        return Err(ChainMuxerError::TipsetChannelSend(why.to_string()));
-                    };
+                    enqueue_tipset(
+                        &tipset_sender,
+                        &tipset_receiver,
+                        Arc::new(tipset.into_tipset()),
+                    )?;
                 }
             },
         );
@@ -1042,3 +1169,63 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{CachingBlockHeader, RawBlockHeader};
+    use crate::shim::address::Address;
+
+    fn tipset(epoch: i64, miner: u64) -> Arc<Tipset> {
+        Arc::new(Tipset::from(CachingBlockHeader::new(RawBlockHeader {
+            epoch,
+            miner_address: Address::new_id(miner),
+            ..Default::default()
+        })))
+    }
+
+    #[test]
+    fn enqueue_tipset_drops_duplicates_and_lowest_epochs() {
+        let (sender, receiver) = flume::bounded(3);
+        for ts in [tipset(5, 1000), tipset(3, 1000), tipset(5, 1000)] {
+            enqueue_tipset(&sender, &receiver, ts).unwrap();
+        }
+        // Full: the duplicate goes first, then the lowest epoch.
+        enqueue_tipset(&sender, &receiver, tipset(4, 1000)).unwrap();
+        enqueue_tipset(&sender, &receiver, tipset(6, 1000)).unwrap();
+
+        let epochs = receiver.drain().map(|ts| ts.epoch()).collect_vec();
+        assert_eq!(epochs, [4, 5, 6]);
+
+        // Sending fails once the tipset processor is gone.
+        let other = flume::bounded(1).1;
+        drop(receiver);
+        assert!(enqueue_tipset(&sender, &other, tipset(7, 1000)).is_err());
+    }
+
+    #[test]
+    fn enqueue_tipset_competes_with_other_senders() {
+        let (sender, receiver) = flume::bounded(4);
+        let done = Arc::new(std::sync::atomic::AtomicBool::new(false));
+        let competitor = std::thread::spawn({
+            let sender = sender.clone();
+            let done = done.clone();
+            move || {
+                let mut miner = 2000;
+                while !done.load(std::sync::atomic::Ordering::Relaxed) {
+                    let _ = sender.try_send(tipset(1, miner));
+                    miner += 1;
+                }
+            }
+        });
+        for epoch in 2..500 {
+            enqueue_tipset(&sender, &receiver, tipset(epoch, 1000)).unwrap();
+        }
+        done.store(true, std::sync::atomic::Ordering::Relaxed);
+        competitor.join().unwrap();
+
+        let epochs = receiver.drain().map(|ts| ts.epoch()).collect_vec();
+        assert!(epochs.len() <= 4);
+        assert!(epochs.contains(&499));
+    }
+}
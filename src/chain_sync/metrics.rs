@@ -54,6 +54,15 @@ pub static INVALID_TIPSET_TOTAL: Lazy<Counter> = Lazy::new(|| {
     );
     metric
 });
+pub static EQUIVOCATING_BLOCK_TOTAL: Lazy<Counter> = Lazy::new(|| {
+    let metric = Counter::default();
+    crate::metrics::default_registry().register(
+        "equivocating_block_total",
+        "Total number of valid blocks produced by a miner that already has a different block at the same epoch",
+        metric.clone(),
+    );
+    metric
+});
 pub static TIPSET_RANGE_SYNC_FAILURE_TOTAL: Lazy<Counter> = Lazy::new(|| {
     let metric = Counter::default();
     crate::metrics::default_registry().register(
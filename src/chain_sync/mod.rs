@@ -10,6 +10,7 @@ mod sync_state;
 mod tipset_syncer;
 mod validation;
 
+pub use tipset_syncer::{block_size_checks, block_timestamp_checks};
 pub use validation::TipsetValidator;
 
 pub use self::{
@@ -12,7 +12,6 @@ use std::{
 };
 
 use crate::networks::Height;
-use crate::shim::clock::ALLOWABLE_CLOCK_DRIFT;
 use crate::shim::{
     address::Address, clock::ChainEpoch, crypto::verify_bls_aggregate, econ::BLOCK_GAS_LIMIT,
     gas::price_list_by_network_version, message::Message, state_tree::StateTree,
@@ -20,7 +19,10 @@ use crate::shim::{
 use crate::state_manager::{is_valid_for_sending, Error as StateManagerError, StateManager};
 use crate::utils::io::WithProgressRaw;
 use crate::{
-    blocks::{Block, CachingBlockHeader, Error as ForestBlockError, FullTipset, Tipset, TipsetKey},
+    blocks::{
+        Block, CachingBlockHeader, Error as ForestBlockError, FullTipset, RawBlockHeader, Tipset,
+        TipsetKey,
+    },
     fil_cns::{self, FilecoinConsensus, FilecoinConsensusError},
 };
 use crate::{
@@ -73,6 +75,10 @@ pub enum TipsetRangeSyncerError {
     BlockWithoutBlsAggregate,
     #[error("Block received from the future: now = {0}, block = {1}")]
     TimeTravellingBlock(u64, u64),
+    #[error("Block header is too large: size = {0}, max = {1}")]
+    BlockHeaderTooLarge(usize, usize),
+    #[error("Equivocating block: {0}")]
+    EquivocatingBlock(String),
     #[error("Tipset range contains bad block [block = {0}]: {1}")]
     TipsetRangeWithBadBlock(Cid, String),
     #[error("Validation error: {0}")]
@@ -1203,6 +1209,24 @@ async fn validate_tipset<DB: Blockstore + Send + Sync + 'static>(
     while let Some(result) = validations.next().await {
         match result? {
             Ok(block) => {
+                let equivocations = chainstore.equivocating_blocks(block.header());
+                if !equivocations.is_empty() {
+                    let equivocation = format!(
+                        "miner {} produced blocks {} and {} at epoch {epoch}",
+                        block.header().miner_address,
+                        block.cid(),
+                        equivocations.iter().join(", "),
+                    );
+                    metrics::EQUIVOCATING_BLOCK_TOTAL.inc();
+                    error!("Equivocation detected: {equivocation}");
+                    if state_manager.sync_config().reject_equivocating_blocks {
+                        let why = TipsetRangeSyncerError::EquivocatingBlock(equivocation);
+                        if let InvalidBlockStrategy::Strict = invalid_block_strategy {
+                            bad_block_cache.put(*block.cid(), why.to_string());
+                        }
+                        return Err(why);
+                    }
+                }
                 chainstore.add_to_tipset_tracker(block.header());
             }
             Err((cid, why)) => {
@@ -1275,7 +1299,14 @@ async fn validate_block<DB: Blockstore + Sync + Send + 'static>(
 
     // Check to ensure all optional values exist
     block_sanity_checks(header).map_err(|e| (*block_cid, e))?;
-    block_timestamp_checks(header).map_err(|e| (*block_cid, e))?;
+    block_size_checks(header, state_manager.sync_config().max_block_header_size)
+        .map_err(|e| (*block_cid, e))?;
+    block_timestamp_checks(
+        header,
+        chrono::Utc::now().timestamp() as u64,
+        state_manager.sync_config().allowable_clock_drift(),
+    )
+    .map_err(|e| (*block_cid, e))?;
 
     let base_tipset = chain_store
         .chain_index
@@ -1320,6 +1351,7 @@ async fn validate_block<DB: Blockstore + Sync + Send + 'static>(
 
     // Base fee check
     let smoke_height = state_manager.chain_config().epoch(Height::Smoke);
+    let min_base_fee = state_manager.chain_config().min_base_fee.clone();
     let v_base_tipset = Arc::clone(&base_tipset);
     let v_block_store = state_manager.blockstore_owned();
     let v_block = Arc::clone(&block);
@@ -1327,10 +1359,15 @@ async fn validate_block<DB: Blockstore + Sync + Send + 'static>(
         let metric =
             &*metrics::BLOCK_VALIDATION_TASKS_TIME.get_or_create(&metrics::values::BASE_FEE_CHECK);
         let _timer = metric.start_timer();
-        let base_fee = crate::chain::compute_base_fee(&v_block_store, &v_base_tipset, smoke_height)
-            .map_err(|e| {
-                TipsetRangeSyncerError::Validation(format!("Could not compute base fee: {e}"))
-            })?;
+        let base_fee = crate::chain::compute_base_fee(
+            &v_block_store,
+            &v_base_tipset,
+            smoke_height,
+            min_base_fee.as_ref(),
+        )
+        .map_err(|e| {
+            TipsetRangeSyncerError::Validation(format!("Could not compute base fee: {e}"))
+        })?;
         let parent_base_fee = &v_block.header.parent_base_fee;
         if &base_fee != parent_base_fee {
             return Err(TipsetRangeSyncerError::Validation(format!(
@@ -1599,10 +1636,32 @@ fn block_sanity_checks(header: &CachingBlockHeader) -> Result<(), TipsetRangeSyn
     Ok(())
 }
 
-/// Check the clock drift.
-fn block_timestamp_checks(header: &CachingBlockHeader) -> Result<(), TipsetRangeSyncerError> {
-    let time_now = chrono::Utc::now().timestamp() as u64;
-    if header.timestamp > time_now.saturating_add(ALLOWABLE_CLOCK_DRIFT) {
+/// Check the serialized size of the header against the configured maximum, if
+/// any.
+pub fn block_size_checks(
+    header: &RawBlockHeader,
+    max_size: Option<usize>,
+) -> Result<(), TipsetRangeSyncerError> {
+    let Some(max_size) = max_size else {
+        return Ok(());
+    };
+    let size = to_vec(header)
+        .map_err(|e| TipsetRangeSyncerError::Calculation(e.to_string()))?
+        .len();
+    if size > max_size {
+        return Err(TipsetRangeSyncerError::BlockHeaderTooLarge(size, max_size));
+    }
+    Ok(())
+}
+
+/// Check the clock drift against `time_now`, in seconds since the Unix epoch,
+/// allowing the block to be up to `allowable_drift` seconds ahead.
+pub fn block_timestamp_checks(
+    header: &RawBlockHeader,
+    time_now: u64,
+    allowable_drift: u64,
+) -> Result<(), TipsetRangeSyncerError> {
+    if header.timestamp > time_now.saturating_add(allowable_drift) {
         return Err(TipsetRangeSyncerError::TimeTravellingBlock(
             time_now,
             header.timestamp,
@@ -1640,6 +1699,7 @@ mod test {
     use crate::blocks::VRFProof;
     use crate::blocks::{CachingBlockHeader, ElectionProof, Ticket, Tipset};
     use crate::shim::address::Address;
+    use crate::shim::clock::ALLOWABLE_CLOCK_DRIFT;
     use cid::Cid;
     use num_bigint::BigInt;
 
@@ -1691,4 +1751,116 @@ mod test {
         assert_eq!(ts, ts3);
         assert_eq!(ts.weight(), &BigInt::from(10));
     }
+
+    #[test]
+    fn test_block_size_checks() {
+        use crate::beacon::BeaconEntry;
+
+        let header = mock_block(1234561, 10, 1);
+        let size = to_vec(&*header).unwrap().len();
+        assert!(block_size_checks(&header, None).is_ok());
+        assert!(block_size_checks(&header, Some(size)).is_ok());
+
+        let oversized = RawBlockHeader {
+            beacon_entries: vec![BeaconEntry::new(1, vec![0; 96]); 100],
+            ..header.into_raw()
+        };
+        assert!(matches!(
+            block_size_checks(&oversized, Some(size)),
+            Err(TipsetRangeSyncerError::BlockHeaderTooLarge(actual, max)) if actual > max && max == size
+        ));
+    }
+
+    #[test]
+    fn test_block_timestamp_checks() {
+        let header = RawBlockHeader {
+            timestamp: 1000,
+            ..Default::default()
+        };
+        let drift = ALLOWABLE_CLOCK_DRIFT;
+        assert!(block_timestamp_checks(&header, 1000, drift).is_ok());
+        assert!(block_timestamp_checks(&header, 1000 - drift, drift).is_ok());
+
+        // A clock running behind the block (or a block ahead of the clock).
+        let skewed = 1000 - drift - 1;
+        assert!(matches!(
+            block_timestamp_checks(&header, skewed, drift),
+            Err(TipsetRangeSyncerError::TimeTravellingBlock(now, 1000)) if now == skewed
+        ));
+        // Unless more drift is allowed.
+        assert!(block_timestamp_checks(&header, skewed, drift + 1).is_ok());
+    }
+
+    #[tokio::test]
+    async fn validate_tipset_rejects_equivocating_blocks() {
+        use crate::blocks::{Block, FullTipset};
+        use crate::chain_sync::SyncConfig;
+        use crate::db::MemoryDB;
+        use crate::networks::ChainConfig;
+        use crate::utils::db::CborStoreExt as _;
+        use std::num::NonZeroUsize;
+
+        let db = Arc::new(MemoryDB::default());
+        let genesis = Tipset::from(CachingBlockHeader::new(RawBlockHeader {
+            timestamp: 1,
+            ..Default::default()
+        }));
+        let cs = Arc::new(
+            ChainStore::new(
+                db.clone(),
+                db.clone(),
+                db.clone(),
+                Arc::new(ChainConfig::default()),
+                genesis.block_headers().first().clone(),
+            )
+            .unwrap(),
+        );
+        let sync_config = SyncConfig {
+            reject_equivocating_blocks: true,
+            ..Default::default()
+        };
+        let sm = Arc::new(
+            StateManager::new(cs.clone(), cs.chain_config().clone(), Arc::new(sync_config))
+                .unwrap(),
+        );
+
+        // Two blocks of the same miner at the same epoch, both of which pass
+        // block validation on their own.
+        let block = |ticket_sequence| {
+            let header = CachingBlockHeader::new(RawBlockHeader {
+                epoch: 1,
+                parents: genesis.key().clone(),
+                ..mock_block(1000, 10, ticket_sequence).into_raw()
+            });
+            db.put_cbor_default(&header).unwrap();
+            cs.mark_block_as_validated(header.cid());
+            Block {
+                header,
+                bls_messages: vec![],
+                secp_messages: vec![],
+            }
+        };
+        let first = block(1);
+        let second = block(2);
+        cs.add_to_tipset_tracker(&first.header);
+
+        let bad_block_cache = BadBlockCache::new(NonZeroUsize::new(8).unwrap());
+        let before = metrics::EQUIVOCATING_BLOCK_TOTAL.get();
+        let result = validate_tipset(
+            sm,
+            &cs,
+            &bad_block_cache,
+            FullTipset::new([second.clone()]).unwrap(),
+            &genesis,
+            InvalidBlockStrategy::Strict,
+        )
+        .await;
+        assert!(
+            matches!(result, Err(TipsetRangeSyncerError::EquivocatingBlock(_))),
+            "{result:?}"
+        );
+        assert_eq!(metrics::EQUIVOCATING_BLOCK_TOTAL.get(), before + 1);
+        assert!(bad_block_cache.peek(second.cid()).is_some());
+        assert_eq!(cs.equivocating_blocks(&first.header), vec![]);
+    }
 }
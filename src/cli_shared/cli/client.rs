@@ -38,6 +38,9 @@ impl Default for BufferSize {
 pub struct Client {
     pub data_dir: PathBuf,
     pub genesis_file: Option<String>,
+    /// URL to download the genesis CAR from when `genesis_file` is unset. The
+    /// genesis must match the chain's expected genesis CID.
+    pub genesis_url: Option<String>,
     pub enable_rpc: bool,
     pub enable_metrics_endpoint: bool,
     pub enable_health_check: bool,
@@ -76,6 +79,7 @@ impl Default for Client {
         Self {
             data_dir: dir.data_dir().to_path_buf(),
             genesis_file: None,
+            genesis_url: None,
             enable_rpc: true,
             enable_metrics_endpoint: true,
             enable_health_check: true,
@@ -3,6 +3,7 @@
 
 use crate::db::db_engine::DbConfig;
 use crate::libp2p::Libp2pConfig;
+use crate::message_pool::MpoolPolicy;
 use crate::{chain_sync::SyncConfig, networks::NetworkChain};
 use serde::{Deserialize, Serialize};
 use std::path::PathBuf;
@@ -45,6 +46,7 @@ pub struct Config {
     pub parity_db: crate::db::parity_db_config::ParityDbConfig,
     pub network: Libp2pConfig,
     pub sync: SyncConfig,
+    pub mpool: MpoolPolicy,
     pub daemon: DaemonConfig,
 }
 
@@ -60,6 +62,52 @@ mod test {
 
     use super::*;
 
+    #[test]
+    fn mpool_section_overrides_stored_config() {
+        use crate::message_pool::MpoolConfig;
+        use crate::shim::econ::TokenAmount;
+
+        let config: Config = toml::from_str(
+            r#"
+            [mpool]
+            max_message_age_epochs = 120
+            sender_allowlist = ["f01234", "f1abjxfbp274xpdqcpuaykwkfb43omjotacm2p3za"]
+            min_gas_fee_cap = "1000"
+            reject_fee_cap_below_base_fee = true
+            max_pending_submissions = 16
+            "#,
+        )
+        .unwrap();
+        let mpool_config = MpoolConfig::default().with_policy(&config.mpool);
+        assert_eq!(mpool_config.max_message_age_epochs, Some(120));
+        assert_eq!(
+            mpool_config.sender_allowlist.unwrap(),
+            [
+                "f01234".parse().unwrap(),
+                "f1abjxfbp274xpdqcpuaykwkfb43omjotacm2p3za".parse().unwrap()
+            ]
+        );
+        assert_eq!(
+            mpool_config.min_gas_fee_cap,
+            Some(TokenAmount::from_atto(1000))
+        );
+        assert!(mpool_config.reject_fee_cap_below_base_fee);
+        assert_eq!(mpool_config.max_pending_submissions, Some(16));
+
+        let config: Config = toml::from_str("").unwrap();
+        let mpool_config = MpoolConfig::default().with_policy(&config.mpool);
+        assert_eq!(mpool_config.max_message_age_epochs, None);
+        assert_eq!(mpool_config.sender_allowlist, None);
+        assert_eq!(mpool_config.min_gas_fee_cap, None);
+        assert!(!mpool_config.reject_fee_cap_below_base_fee);
+        assert_eq!(mpool_config.max_pending_submissions, None);
+
+        let config: Config = toml::from_str("[mpool]\nsender_allowlist = []").unwrap();
+        assert_eq!(config.mpool.sender_allowlist, Some(vec![]));
+        let config: Config = toml::from_str(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(config.mpool.sender_allowlist, Some(vec![]));
+    }
+
     #[quickcheck]
     fn test_config_all_params_under_section(config: Config) {
         let serialized_config =
@@ -79,7 +79,8 @@ mod tests {
     #[test]
     fn read_config_with_path() {
         let default_config = Config::default();
-        let path: PathBuf = "config.toml".into();
+        let dir = tempfile::tempdir().unwrap();
+        let path: PathBuf = dir.path().join("config.toml");
         let serialized_config = toml::to_string(&default_config).unwrap();
         std::fs::write(path.clone(), serialized_config).unwrap();
 
@@ -21,7 +21,7 @@ use crate::daemon::db_util::{
 use crate::db::car::ManyCar;
 use crate::db::db_engine::{db_root, open_db};
 use crate::db::{ttl::EthMappingCollector, MarkAndSweep, MemoryDB, SettingsExt, CAR_DB_DIR_NAME};
-use crate::genesis::{get_network_name_from_genesis, read_genesis_header};
+use crate::genesis::{fetch_genesis, get_network_name_from_genesis, read_genesis_header};
 use crate::key_management::{
     KeyStore, KeyStoreConfig, ENCRYPTED_KEYSTORE_NAME, FOREST_KEYSTORE_PHRASE_ENV,
 };
@@ -145,7 +145,8 @@ pub(super) async fn start(
     config: Config,
     shutdown_send: mpsc::Sender<()>,
 ) -> anyhow::Result<()> {
-    let chain_config = Arc::new(ChainConfig::from_chain(&config.chain));
+    let chain_config =
+        Arc::new(ChainConfig::from_chain(&config.chain).with_upgrade_schedule_from_env()?);
     if chain_config.is_testnet() {
         CurrentNetwork::set_global(Network::Testnet);
     }
@@ -202,13 +203,45 @@ pub(super) async fn start(
     // Read Genesis file
     // * When snapshot command implemented, this genesis does not need to be
     //   initialized
+    let genesis_bytes = match (&config.client.genesis_file, &config.client.genesis_url) {
+        (None, Some(url)) => {
+            let expected = chain_config
+                .genesis_cid
+                .as_deref()
+                .context("fetching genesis from a URL requires a known genesis CID (set FOREST_GENESIS_CID on devnets)")?
+                .parse()?;
+            Some(fetch_genesis(&url.parse()?, &expected, &db).await?)
+        }
+        _ => chain_config.genesis_bytes(&db).await?,
+    };
     let genesis_header = read_genesis_header(
         config.client.genesis_file.as_ref(),
-        chain_config.genesis_bytes(&db).await?.as_deref(),
+        genesis_bytes.as_deref(),
         &db,
     )
     .await?;
 
+    // Initialize ChainStore
+    let chain_store = Arc::new(ChainStore::new(
+        Arc::clone(&db),
+        db.writer().clone(),
+        db.writer().clone(),
+        chain_config.clone(),
+        genesis_header.clone(),
+    )?);
+
+    // Initialize StateManager
+    let sm = StateManager::new(
+        Arc::clone(&chain_store),
+        Arc::clone(&chain_config),
+        Arc::new(config.sync.clone()),
+    )?;
+
+    let state_manager = Arc::new(sm);
+    state_manager
+        .validate_network_name(&state_manager.chain_store().heaviest_tipset())
+        .context("chain head doesn't match the configured network")?;
+
     if config.client.enable_metrics_endpoint {
         // Start Prometheus server port
         let prometheus_listener = TcpListener::bind(config.client.metrics_address)
@@ -234,16 +267,8 @@ pub(super) async fn start(
         ));
     }
 
-    // Initialize ChainStore
-    let chain_store = Arc::new(ChainStore::new(
-        Arc::clone(&db),
-        db.writer().clone(),
-        db.writer().clone(),
-        chain_config.clone(),
-        genesis_header.clone(),
-    )?);
-
-    if !opts.no_gc {
+    let gc_requests = if !opts.no_gc {
+        let (gc_request_send, gc_request_recv) = flume::unbounded();
         let mut db_garbage_collector = {
             let chain_store = chain_store.clone();
             let depth = cmp::max(
@@ -261,8 +286,15 @@ pub(super) async fn start(
             )
         };
 
-        services.spawn(async move { db_garbage_collector.gc_loop(GC_INTERVAL).await });
-    }
+        services.spawn(async move {
+            db_garbage_collector
+                .gc_loop(GC_INTERVAL, gc_request_recv)
+                .await
+        });
+        Some(gc_request_send)
+    } else {
+        None
+    };
 
     if let Some(ttl) = config.client.eth_mapping_ttl {
         let chain_store = chain_store.clone();
@@ -281,20 +313,11 @@ pub(super) async fn start(
 
     let publisher = chain_store.publisher();
 
-    // Initialize StateManager
-    let sm = StateManager::new(
-        Arc::clone(&chain_store),
-        Arc::clone(&chain_config),
-        Arc::new(config.sync.clone()),
-    )?;
-
-    let state_manager = Arc::new(sm);
-
     let network_name = get_network_name_from_genesis(&genesis_header, &state_manager)?;
 
     info!("Using network :: {}", get_actual_chain_name(&network_name));
     display_chain_logo(&config.chain);
-    let (tipset_sender, tipset_receiver) = flume::bounded(20);
+    let (tipset_sender, tipset_receiver) = flume::bounded(config.sync.pending_tipsets_capacity());
 
     // if bootstrap peers are not set, set them
     let config = if config.network.bootstrap_peers.is_empty() {
@@ -340,7 +363,7 @@ pub(super) async fn start(
         provider,
         network_name.clone(),
         network_send.clone(),
-        MpoolConfig::load_config(db.writer().as_ref())?,
+        MpoolConfig::load_config(db.writer().as_ref())?.with_policy(&config.mpool),
         state_manager.chain_config().clone(),
         &mut services,
     )?;
@@ -404,6 +427,7 @@ pub(super) async fn start(
                     start_time,
                     shutdown: shutdown_send,
                     tipset_send: tipset_sender,
+                    gc_requests,
                 },
                 rpc_address,
             )
@@ -445,6 +469,9 @@ pub(super) async fn start(
             .await?;
             db.read_only_files(std::iter::once(car_db_path.clone()))?;
             debug!("Loaded car DB at {}", car_db_path.display());
+            state_manager
+                .validate_network_name(&ts)
+                .context("snapshot doesn't match the configured network")?;
             state_manager
                 .chain_store()
                 .set_heaviest_tipset(Arc::new(ts.clone()))?;
@@ -86,6 +86,10 @@ use tracing::{error, info};
 
 const SETTINGS_KEY: &str = "LAST_GC_RUN";
 
+/// Request for an immediate GC run, see [`MarkAndSweep::gc_now`]. The result is
+/// sent back through the enclosed channel.
+pub type GcRequest = flume::Sender<anyhow::Result<Option<u32>>>;
+
 /// [`MarkAndSweep`] is a simple garbage collector implementation that traverses all the database
 /// keys writing them to a [`CidHashSet`], then filters out those that need to be kept and schedules
 /// the rest for removal.
@@ -161,14 +165,59 @@ impl<DB: Blockstore + SettingsStore + GarbageCollectable<CidHashSet> + Sync + Se
     ///
     /// NOTE: This currently does not take into account the fact that we might be starting the node
     /// using CAR-backed storage with a snapshot, for implementation simplicity.
-    pub async fn gc_loop(&mut self, interval: Duration) -> anyhow::Result<()> {
+    /// Runs the GC periodically, and immediately whenever a request is received
+    /// on `requests`.
+    pub async fn gc_loop(
+        &mut self,
+        interval: Duration,
+        requests: flume::Receiver<GcRequest>,
+    ) -> anyhow::Result<()> {
         loop {
-            if let Err(err) = self.gc_workflow(interval).await {
-                error!("GC run error: {}", err)
+            // Interrupting the scheduled workflow is fine, as `gc_now` always
+            // filters the marked set again before sweeping.
+            let request = tokio::select! {
+                result = self.gc_workflow(interval) => {
+                    if let Err(err) = result {
+                        error!("GC run error: {}", err)
+                    }
+                    None
+                }
+                Ok(request) = requests.recv_async() => Some(request),
+            };
+            if let Some(request) = request {
+                let _ = request.send(self.gc_now().await);
             }
         }
     }
 
+    /// Runs a GC cycle right away instead of waiting for the schedule. Records
+    /// are only swept if they were marked at least `depth` epochs ago, so that
+    /// no data younger than chain finality is removed. Otherwise this only
+    /// marks the database and returns `None`. Returns the number of deleted
+    /// records on sweep.
+    pub async fn gc_now(&mut self) -> anyhow::Result<Option<u32>> {
+        let tipset = (self.get_heaviest_tipset)();
+        let current_epoch = tipset.epoch();
+        if self.marked.is_empty() {
+            info!("populate keys for GC");
+            self.populate()?;
+            self.epoch_marked = current_epoch;
+        }
+        if current_epoch - self.epoch_marked < self.depth {
+            return Ok(None);
+        }
+
+        info!("filter keys for GC");
+        self.filter(tipset, self.depth).await?;
+
+        info!("GC sweep");
+        let deleted = self.sweep()?;
+        info!("GC finished sweep: {} deleted records", deleted);
+
+        self.update_last_gc_run(current_epoch)?;
+        Ok(Some(deleted))
+    }
+
     fn update_last_gc_run(&self, epoch: ChainEpoch) -> anyhow::Result<()> {
         self.db
             .write_bin(SETTINGS_KEY, epoch.to_string().as_bytes())
@@ -467,4 +516,32 @@ mod test {
             current_epoch + 1 + depth * 2
         );
     }
+
+    #[tokio::test]
+    async fn gc_now_sweeps_once_mark_is_final() {
+        let depth = 5;
+        let unreachable_nodes = 3;
+
+        let tester = GCTester::new();
+        let mut gc = MarkAndSweep::new(
+            tester.db.clone(),
+            tester.get_heaviest_tipset_fn(),
+            depth,
+            ZERO_DURATION,
+        );
+
+        tester.run_epochs(depth);
+        tester.insert_unreachable(unreachable_nodes);
+        // Mark only, the mark is not old enough to sweep yet.
+        assert_eq!(gc.gc_now().await.unwrap(), None);
+        tester.run_epochs(depth);
+
+        // Sweep.
+        assert_eq!(gc.gc_now().await.unwrap(), Some(unreachable_nodes as u32));
+        assert_eq!(
+            tester.db.get_keys().unwrap().len() as i64,
+            // `Genesis block + twice the depth.`
+            1 + depth * 2
+        );
+    }
 }
@@ -3,13 +3,15 @@
 
 pub mod car;
 mod memory;
+mod overlay;
 pub mod parity_db;
 pub mod parity_db_config;
 
 mod gc;
 pub mod ttl;
-pub use gc::MarkAndSweep;
+pub use gc::{GcRequest, MarkAndSweep};
 pub use memory::MemoryDB;
+pub use overlay::MemoryOverlay;
 use setting_keys::ETH_MAPPING_UP_TO_DATE_KEY;
 mod db_mode;
 pub mod migration;
@@ -0,0 +1,58 @@
+// Copyright 2019-2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use cid::Cid;
+use fvm_ipld_blockstore::Blockstore;
+
+use super::MemoryDB;
+
+/// A blockstore that reads through to `base` but keeps all writes in memory.
+/// Dropping it discards everything written to it, leaving `base` untouched.
+pub struct MemoryOverlay<DB> {
+    base: DB,
+    overlay: MemoryDB,
+}
+
+impl<DB> MemoryOverlay<DB> {
+    pub fn new(base: DB) -> Self {
+        Self {
+            base,
+            overlay: MemoryDB::default(),
+        }
+    }
+}
+
+impl<DB: Blockstore> Blockstore for MemoryOverlay<DB> {
+    fn get(&self, k: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+        match self.overlay.get(k)? {
+            Some(block) => Ok(Some(block)),
+            None => self.base.get(k),
+        }
+    }
+
+    fn has(&self, k: &Cid) -> anyhow::Result<bool> {
+        Ok(self.overlay.has(k)? || self.base.has(k)?)
+    }
+
+    fn put_keyed(&self, k: &Cid, block: &[u8]) -> anyhow::Result<()> {
+        self.overlay.put_keyed(k, block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::db::CborStoreExt as _;
+
+    #[test]
+    fn writes_stay_in_the_overlay() {
+        let base = MemoryDB::default();
+        let below = base.put_cbor_default(&"below").unwrap();
+        let overlay = MemoryOverlay::new(&base);
+
+        let above = overlay.put_cbor_default(&"above").unwrap();
+        assert!(overlay.has(&below).unwrap());
+        assert!(overlay.has(&above).unwrap());
+        assert!(!base.has(&above).unwrap());
+    }
+}
@@ -133,11 +133,18 @@ pub(in crate::fil_cns) async fn validate_block<DB: Blockstore + Sync + Send + 's
             let parent_epoch = base_tipset.epoch();
             let prev_beacon = Arc::clone(&prev_beacon);
             let nv = state_manager.get_network_version(header.epoch);
+            let state_manager = Arc::clone(&state_manager);
             async move {
-                block
-                    .header()
+                let header = block.header();
+                header
                     .validate_block_drand(nv, beacon_schedule.as_ref(), parent_epoch, &prev_beacon)
-                    .map_err(|e| FilecoinConsensusError::BeaconValidation(e.to_string()))
+                    .map_err(|e| FilecoinConsensusError::BeaconValidation(e.to_string()))?;
+                if let Some(entry) = header.beacon_entries.last() {
+                    state_manager
+                        .cache_beacon_entry(header.epoch, entry)
+                        .map_err(|e| FilecoinConsensusError::BeaconValidation(e.to_string()))?;
+                }
+                Ok(())
             }
         }));
     }
@@ -2,9 +2,13 @@
 // SPDX-License-Identifier: Apache-2.0, MIT
 
 use crate::blocks::CachingBlockHeader;
+use crate::db::SettingsStore;
 use crate::state_manager::StateManager;
+use crate::utils::db::car_stream::CarStream;
 use crate::utils::db::car_util::load_car;
+use crate::utils::net::http_get;
 use anyhow::Context as _;
+use cid::Cid;
 use fvm_ipld_blockstore::Blockstore;
 use tokio::{fs::File, io::AsyncBufRead, io::BufReader};
 use tracing::{debug, info};
@@ -39,6 +43,28 @@ where
     Ok(genesis)
 }
 
+/// Downloads the genesis CAR from `url`, unless a copy rooted at `expected` is
+/// already cached in the settings store. The downloaded CAR must have
+/// `expected` as its only root. The returned bytes may be zstd-compressed.
+pub async fn fetch_genesis<DB: SettingsStore>(
+    url: &url::Url,
+    expected: &Cid,
+    db: &DB,
+) -> anyhow::Result<Vec<u8>> {
+    let genesis_key = format!("GENESIS-{expected}");
+    if let Some(genesis) = db.read_bin(&genesis_key)? {
+        return Ok(genesis);
+    }
+    let genesis = http_get(url).await?.bytes().await?;
+    let roots = CarStream::new(genesis.as_ref()).await?.header.roots;
+    anyhow::ensure!(
+        roots.len() == 1 && roots.first() == expected,
+        "genesis downloaded from {url} is rooted at {roots:?}, expected {expected}"
+    );
+    db.write_bin(&genesis_key, &genesis)?;
+    Ok(genesis.to_vec())
+}
+
 pub fn get_network_name_from_genesis<BS>(
     genesis_header: &CachingBlockHeader,
     state_manager: &StateManager<BS>,
@@ -76,7 +102,6 @@ where
 #[cfg(test)]
 mod tests {
     use super::*;
-    use cid::Cid;
 
     #[tokio::test]
     async fn test_process_car_genesis_mainnet() {
@@ -98,4 +123,32 @@ mod tests {
         let db = crate::db::MemoryDB::default();
         process_car(genesis_bytes, &db).await.unwrap()
     }
+
+    async fn serve_genesis(genesis: &'static [u8]) -> url::Url {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = axum::Router::new().route(
+            "/genesis.car",
+            axum::routing::get(move || async move { genesis }),
+        );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        format!("http://{addr}/genesis.car").parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_fetch_genesis_verifies_root() {
+        use crate::networks::calibnet::{DEFAULT_GENESIS, GENESIS_CID};
+
+        let url = serve_genesis(DEFAULT_GENESIS).await;
+        let db = crate::db::MemoryDB::default();
+
+        let wrong = Cid::default();
+        assert!(fetch_genesis(&url, &wrong, &db).await.is_err());
+        assert!(db.read_bin(&format!("GENESIS-{wrong}")).unwrap().is_none());
+
+        let genesis = fetch_genesis(&url, &GENESIS_CID, &db).await.unwrap();
+        assert_eq!(genesis, DEFAULT_GENESIS);
+        let header = load_header_from_car(&genesis).await;
+        assert_eq!(header.cid(), &GENESIS_CID as &Cid);
+    }
 }
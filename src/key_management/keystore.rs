@@ -101,6 +101,17 @@ struct EncryptedKeyStore {
     encryption_key: Vec<u8>,
 }
 
+/// A single `KeyInfo` encrypted with a passphrase, used to move a key between
+/// key stores without copying the whole store.
+/// `Argon2id` hash key derivation
+/// `XSalsa20Poly1305` authenticated encryption
+/// CBOR encoding
+#[derive(Clone, PartialEq, Debug, Eq, Serialize, Deserialize)]
+pub struct EncryptedKeyExport {
+    salt: SaltByteArray,
+    ciphertext: Vec<u8>,
+}
+
 #[derive(Debug, Error)]
 pub enum EncryptedKeyStoreError {
     /// An error occurred while encrypting keys
@@ -359,6 +370,38 @@ impl KeyStore {
 
         Ok(key_out)
     }
+
+    /// Export the `KeyInfo` that corresponds to a given key, encrypted with
+    /// the given passphrase
+    pub fn export_key(&self, k: &str, passphrase: &str) -> Result<EncryptedKeyExport, Error> {
+        let key_info = self.get(k)?;
+        let (salt, encryption_key) = EncryptedKeyStore::derive_key(passphrase, None)
+            .map_err(|e| Error::Other(format!("failed to derive export key: {e}")))?;
+        let data = serde_ipld_dagcbor::to_vec(&key_info)
+            .map_err(|e| Error::Other(format!("failed to serialize key info: {e}")))?;
+        let ciphertext = EncryptedKeyStore::encrypt(&encryption_key, &data)
+            .map_err(|e| Error::Other(format!("failed to encrypt key info: {e}")))?;
+        Ok(EncryptedKeyExport { salt, ciphertext })
+    }
+
+    /// Decrypt an exported `KeyInfo` with the given passphrase and save it
+    /// under the given key
+    pub fn import_key(
+        &mut self,
+        key: &str,
+        export: &EncryptedKeyExport,
+        passphrase: &str,
+    ) -> Result<(), Error> {
+        let (_, encryption_key) = EncryptedKeyStore::derive_key(passphrase, Some(export.salt))
+            .map_err(|e| Error::Other(format!("failed to derive export key: {e}")))?;
+        let data =
+            EncryptedKeyStore::decrypt(&encryption_key, &export.ciphertext).map_err(|_| {
+                Error::Other("failed to decrypt key info, the passphrase may be wrong".to_owned())
+            })?;
+        let key_info = from_slice_with_fallback(&data)
+            .map_err(|e| Error::Other(format!("failed to deserialize key info: {e}")))?;
+        self.put(key, key_info)
+    }
 }
 
 impl EncryptedKeyStore {
@@ -477,6 +520,38 @@ mod test {
         assert_eq!(plaintext, message.as_bytes());
     }
 
+    #[test]
+    fn test_export_import_key() {
+        for key_type in [SignatureType::Bls, SignatureType::Secp256k1] {
+            let key = wallet::generate_key(key_type).unwrap();
+            let name = format!("wallet-{}", key.address);
+
+            let mut ks = KeyStore::new(KeyStoreConfig::Memory).unwrap();
+            ks.put(&name, key.key_info.clone()).unwrap();
+            let export = ks.export_key(&name, PASSPHRASE).unwrap();
+
+            let mut ks_import = KeyStore::new(KeyStoreConfig::Memory).unwrap();
+            ks_import.import_key(&name, &export, PASSPHRASE).unwrap();
+            assert_eq!(ks_import.get(&name).unwrap(), key.key_info);
+        }
+    }
+
+    #[test]
+    fn test_import_key_wrong_passphrase() {
+        let key = wallet::generate_key(SignatureType::Secp256k1).unwrap();
+        let name = format!("wallet-{}", key.address);
+
+        let mut ks = KeyStore::new(KeyStoreConfig::Memory).unwrap();
+        ks.put(&name, key.key_info).unwrap();
+        let export = ks.export_key(&name, PASSPHRASE).unwrap();
+
+        let mut ks_import = KeyStore::new(KeyStoreConfig::Memory).unwrap();
+        assert!(ks_import
+            .import_key(&name, &export, "wrong passphrase")
+            .is_err());
+        assert!(ks_import.list().is_empty());
+    }
+
     #[test]
     fn test_read_old_encrypted_keystore() {
         let dir: PathBuf = "src/key_management/tests/keystore_encrypted_old".into();
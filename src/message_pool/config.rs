@@ -5,7 +5,7 @@ use std::time::Duration;
 
 use crate::{
     db::{setting_keys::MPOOL_CONFIG_KEY, SettingsStore},
-    shim::address::Address,
+    shim::{address::Address, clock::ChainEpoch, econ::TokenAmount},
     utils::encoding::from_slice_with_fallback,
 };
 use serde::{Deserialize, Serialize};
@@ -27,6 +27,29 @@ pub struct MpoolConfig {
     pub replace_by_fee_ratio: f64,
     pub prune_cooldown: Duration,
     pub gas_limit_overestimation: f64,
+    /// Number of epochs after which a pending message is dropped from the
+    /// pool, along with the later messages of its sender. Checked on every
+    /// head change. Messages never expire when unset.
+    #[serde(default)]
+    pub max_message_age_epochs: Option<ChainEpoch>,
+    /// Senders allowed to submit messages to the pool, as ID or key
+    /// addresses. Anyone can submit messages when unset.
+    #[serde(default)]
+    pub sender_allowlist: Option<Vec<Address>>,
+    /// Minimum `GasFeeCap` of messages accepted by the pool, on top of the
+    /// protocol minimum base fee.
+    #[serde(default)]
+    pub min_gas_fee_cap: Option<TokenAmount>,
+    /// Reject messages whose `GasFeeCap` is below the base fee of the current
+    /// head, as they cannot be included in the next block.
+    #[serde(default)]
+    pub reject_fee_cap_below_base_fee: bool,
+    /// Maximum number of message submissions waiting to be validated. When
+    /// set, submissions are queued and validated one at a time, and further
+    /// submissions are rejected with `TooManySubmissions` while the queue is
+    /// full. Submissions are validated as they arrive when unset.
+    #[serde(default)]
+    pub max_pending_submissions: Option<usize>,
 }
 
 impl Default for MpoolConfig {
@@ -38,6 +61,11 @@ impl Default for MpoolConfig {
             replace_by_fee_ratio: REPLACE_BY_FEE_RATIO,
             prune_cooldown: PRUNE_COOLDOWN,
             gas_limit_overestimation: GAS_LIMIT_OVERESTIMATION,
+            max_message_age_epochs: None,
+            sender_allowlist: None,
+            min_gas_fee_cap: None,
+            reject_fee_cap_below_base_fee: false,
+            max_pending_submissions: None,
         }
     }
 }
@@ -60,6 +88,39 @@ impl MpoolConfig {
     }
 }
 
+/// Message pool policies set in the `[mpool]` section of the configuration
+/// file. They take precedence over the [`MpoolConfig`] stored in the database.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
+#[serde(default)]
+pub struct MpoolPolicy {
+    /// See [`MpoolConfig::max_message_age_epochs`].
+    pub max_message_age_epochs: Option<ChainEpoch>,
+    /// See [`MpoolConfig::sender_allowlist`].
+    #[serde(with = "sender_allowlist")]
+    pub sender_allowlist: Option<Vec<Address>>,
+    /// See [`MpoolConfig::min_gas_fee_cap`].
+    #[serde(with = "crate::lotus_json")]
+    pub min_gas_fee_cap: Option<TokenAmount>,
+    /// See [`MpoolConfig::reject_fee_cap_below_base_fee`].
+    pub reject_fee_cap_below_base_fee: bool,
+    /// See [`MpoolConfig::max_pending_submissions`].
+    #[cfg_attr(test, arbitrary(gen(|g| Option::<u32>::arbitrary(g).map(|it| it as _))))]
+    pub max_pending_submissions: Option<usize>,
+}
+
+impl MpoolConfig {
+    /// Returns this configuration with the settings of `policy` applied.
+    pub fn with_policy(mut self, policy: &MpoolPolicy) -> Self {
+        self.max_message_age_epochs = policy.max_message_age_epochs;
+        self.sender_allowlist.clone_from(&policy.sender_allowlist);
+        self.min_gas_fee_cap.clone_from(&policy.min_gas_fee_cap);
+        self.reject_fee_cap_below_base_fee = policy.reject_fee_cap_below_base_fee;
+        self.max_pending_submissions = policy.max_pending_submissions;
+        self
+    }
+}
+
 impl MpoolConfig {
     /// Load `config` from store, if exists. If there is no `config`, uses
     /// default.
@@ -70,3 +131,25 @@ impl MpoolConfig {
         }
     }
 }
+
+/// Unlike plain [`crate::lotus_json`], keeps an empty allowlist distinct from
+/// an absent one.
+mod sender_allowlist {
+    use super::*;
+    use crate::lotus_json::{LotusJson, NotNullVec};
+    use serde::{Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<Vec<Address>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        LotusJson(value.clone().map(NotNullVec)).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<Address>>, D::Error> {
+        LotusJson::<Option<NotNullVec<Address>>>::deserialize(deserializer)
+            .map(|LotusJson(it)| it.map(|NotNullVec(it)| it))
+    }
+}
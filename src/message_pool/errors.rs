@@ -28,10 +28,18 @@ pub enum Error {
     InvalidFromAddr,
     #[error("Message with sequence already in mempool")]
     DuplicateSequence,
+    #[error("Invalid message: {0}")]
+    InvalidMessage(String),
+    #[error("Invalid message signature: {0}")]
+    InvalidSignature(String),
     #[error("Validation Error: {0}")]
     SoftValidationFailure(String),
+    #[error("Sender {0} is not allowed to submit messages")]
+    SenderNotAllowed(String),
     #[error("Too many pending messages from actor {0} (trusted: {1})")]
     TooManyPendingMessages(String, bool),
+    #[error("Too many message submissions in progress, try again later")]
+    TooManySubmissions,
     #[error("{0}")]
     Other(String),
 }
@@ -271,8 +271,9 @@ where
     }
     for (_, hm) in rmsgs {
         for (_, msg) in hm {
-            let sequence = get_state_sequence(api, &msg.from(), &cur_tipset.lock().clone())?;
-            if let Err(e) = add_helper(api, bls_sig_cache, pending, msg, sequence) {
+            let cur_ts = cur_tipset.lock().clone();
+            let sequence = get_state_sequence(api, &msg.from(), &cur_ts)?;
+            if let Err(e) = add_helper(api, bls_sig_cache, pending, msg, sequence, cur_ts.epoch()) {
                 error!("Failed to read message from reorg to mpool: {}", e);
             }
         }
@@ -321,7 +322,7 @@ pub mod tests {
     use std::{borrow::BorrowMut, time::Duration};
 
     use crate::blocks::Tipset;
-    use crate::key_management::{KeyStore, KeyStoreConfig, Wallet};
+    use crate::key_management::{generate_key, KeyStore, KeyStoreConfig, Wallet};
     use crate::message::SignedMessage;
     use crate::networks::ChainConfig;
     use crate::shim::{
@@ -337,29 +338,20 @@ pub mod tests {
     use super::*;
     use crate::message_pool::{
         msg_chain::{create_message_chains, Chains},
-        msg_pool::MessagePool,
+        msg_pool::{expire_messages, MessagePool},
+        MpoolConfig,
     };
 
     #[tokio::test]
     async fn test_per_actor_limit() {
-        let keystore = KeyStore::new(KeyStoreConfig::Memory).unwrap();
-        let mut wallet = Wallet::new(keystore);
+        let (mpool, mut wallet, _rx) = mpool_with_api(
+            TestApi::with_max_actor_pending_messages(200),
+            MpoolConfig::default(),
+        );
         let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
         let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
-        let tma = TestApi::with_max_actor_pending_messages(200);
-        tma.set_state_sequence(&sender, 0);
+        mpool.api.set_state_sequence(&sender, 0);
 
-        let (tx, _rx) = flume::bounded(50);
-        let mut services = JoinSet::new();
-        let mpool = MessagePool::new(
-            tma,
-            "mptest".to_string(),
-            tx,
-            Default::default(),
-            Arc::default(),
-            &mut services,
-        )
-        .unwrap();
         let mut smsg_vec = Vec::new();
         for i in 0..(mpool.api.max_actor_pending_messages() + 1) {
             let msg = create_smsg(&target, &sender, wallet.borrow_mut(), i, 1000000, 1);
@@ -427,26 +419,53 @@ pub mod tests {
         signed
     }
 
-    #[tokio::test]
-    async fn test_message_pool() {
-        let keystore = KeyStore::new(KeyStoreConfig::Memory).unwrap();
-        let mut wallet = Wallet::new(keystore);
-        let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
-        let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
-        let tma = TestApi::default();
-        tma.set_state_sequence(&sender, 0);
+    /// Creates a message pool with `config` over a default [`TestApi`],
+    /// along with an empty wallet. Pushes fail once the returned network
+    /// receiver is dropped.
+    fn mpool_with_config(
+        config: MpoolConfig,
+    ) -> (
+        MessagePool<TestApi>,
+        Wallet,
+        flume::Receiver<NetworkMessage>,
+    ) {
+        mpool_with_api(TestApi::default(), config)
+    }
 
-        let (tx, _rx) = flume::bounded(50);
+    /// Like [`mpool_with_config`], over the given `api`. The pool services
+    /// keep running until the test runtime shuts down.
+    fn mpool_with_api(
+        api: TestApi,
+        config: MpoolConfig,
+    ) -> (
+        MessagePool<TestApi>,
+        Wallet,
+        flume::Receiver<NetworkMessage>,
+    ) {
+        let keystore = KeyStore::new(KeyStoreConfig::Memory).unwrap();
+        let wallet = Wallet::new(keystore);
+        let (tx, rx) = flume::bounded(50);
         let mut services = JoinSet::new();
         let mpool = MessagePool::new(
-            tma,
+            api,
             "mptest".to_string(),
             tx,
-            Default::default(),
+            config,
             Arc::default(),
             &mut services,
         )
         .unwrap();
+        services.detach_all();
+        (mpool, wallet, rx)
+    }
+
+    #[tokio::test]
+    async fn test_message_pool() {
+        let (mpool, mut wallet, _rx) = mpool_with_config(MpoolConfig::default());
+        let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        mpool.api.set_state_sequence(&sender, 0);
+
         let mut smsg_vec = Vec::new();
         for i in 0..2 {
             let msg = create_smsg(&target, &sender, wallet.borrow_mut(), i, 1000000, 1);
@@ -487,9 +506,7 @@ pub mod tests {
 
     #[tokio::test]
     async fn test_revert_messages() {
-        let tma = TestApi::default();
-        let keystore = KeyStore::new(KeyStoreConfig::Memory).unwrap();
-        let mut wallet = Wallet::new(keystore);
+        let (mpool, mut wallet, _rx) = mpool_with_config(MpoolConfig::default());
 
         let a = mock_block(1, 1);
         let tipset = Tipset::from(&a);
@@ -504,17 +521,6 @@ pub mod tests {
             let msg = create_smsg(&target, &sender, wallet.borrow_mut(), i, 1000000, 1);
             smsg_vec.push(msg);
         }
-        let (tx, _rx) = flume::bounded(50);
-        let mut services = JoinSet::new();
-        let mpool = MessagePool::new(
-            tma,
-            "mptest".to_string(),
-            tx,
-            Default::default(),
-            Arc::default(),
-            &mut services,
-        )
-        .unwrap();
 
         {
             let mut api_temp = mpool.api.inner.lock();
@@ -596,25 +602,295 @@ pub mod tests {
     }
 
     #[tokio::test]
-    async fn test_async_message_pool() {
-        let keystore = KeyStore::new(KeyStoreConfig::Memory).unwrap();
-        let mut wallet = Wallet::new(keystore);
+    async fn test_pending_contains_pushed_message() {
+        let (mpool, mut wallet, _rx) = mpool_with_config(MpoolConfig::default());
         let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
         let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
 
-        let tma = TestApi::default();
-        tma.set_state_sequence(&sender, 0);
-        let (tx, _rx) = flume::bounded(50);
-        let mut services = JoinSet::new();
-        let mpool = MessagePool::new(
-            tma,
-            "mptest".to_string(),
-            tx,
-            Default::default(),
-            Arc::default(),
-            &mut services,
-        )
-        .unwrap();
+        let (pending, _) = mpool.pending().unwrap();
+        assert!(pending.is_empty());
+
+        let smsg = create_smsg(&target, &sender, wallet.borrow_mut(), 0, 1000000, 1);
+        let cid = mpool.push(smsg.clone()).await.unwrap();
+
+        let (pending, _) = mpool.pending().unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].cid(), cid);
+        assert_eq!(mpool.pending_for(&sender), Some(vec![smsg]));
+    }
+
+    #[tokio::test]
+    async fn test_nonce_gaps() {
+        let (mpool, mut wallet, _rx) = mpool_with_config(MpoolConfig::default());
+        let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+
+        assert!(mpool.nonce_gaps(&sender).unwrap().is_empty());
+
+        for sequence in [0, 2, 4] {
+            let smsg = create_smsg(&target, &sender, wallet.borrow_mut(), sequence, 1000000, 1);
+            mpool.push(smsg).await.unwrap();
+        }
+        assert_eq!(mpool.nonce_gaps(&sender).unwrap(), vec![1, 3]);
+
+        let smsg = create_smsg(&target, &sender, wallet.borrow_mut(), 1, 1000000, 1);
+        mpool.push(smsg).await.unwrap();
+        assert_eq!(mpool.nonce_gaps(&sender).unwrap(), vec![3]);
+    }
+
+    #[tokio::test]
+    async fn test_expire_messages() {
+        let (mpool, mut wallet, _rx) = mpool_with_config(MpoolConfig::default());
+        let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+
+        let smsg = create_smsg(&target, &sender, wallet.borrow_mut(), 0, 1000000, 1);
+        mpool.push(smsg).await.unwrap();
+        let added = mpool.cur_tipset.lock().epoch();
+
+        let expire = |epoch| expire_messages(&mpool.pending, &mpool.local_msgs, epoch, 2);
+        assert_eq!(expire(added + 1), 0);
+        assert_eq!(mpool.pending_for(&sender).unwrap().len(), 1);
+        assert_eq!(mpool.local_msgs.read().len(), 1);
+
+        assert_eq!(expire(added + 2), 1);
+        assert!(mpool.pending_for(&sender).is_none());
+        assert!(mpool.local_msgs.read().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_messages_expire_on_head_change() {
+        let config = MpoolConfig {
+            max_message_age_epochs: Some(2),
+            ..Default::default()
+        };
+        let (mpool, mut wallet, _rx) = mpool_with_config(config);
+        let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        mpool.api.set_state_sequence(&sender, 0);
+
+        let smsg = create_smsg(&target, &sender, wallet.borrow_mut(), 0, 1000000, 1);
+        mpool.push(smsg).await.unwrap();
+
+        // The message is one epoch old after the first head change, and
+        // expires after the second.
+        let mut head = mpool.cur_tipset.lock().clone();
+        for expected_pending in [Some(1), None] {
+            head = Arc::new(Tipset::from(mock_block_with_parents(&head, 1, 1)));
+            mpool.api.set_heaviest_tipset(head.clone());
+            // sleep allows for async block to update mpool's cur_tipset
+            tokio::time::sleep(Duration::new(2, 0)).await;
+            assert_eq!(mpool.cur_tipset.lock().epoch(), head.epoch());
+            assert_eq!(
+                mpool.pending_for(&sender).map(|msgs| msgs.len()),
+                expected_pending
+            );
+        }
+        assert!(mpool.local_msgs.read().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_expire_messages_drops_later_sequences() {
+        let (mpool, mut wallet, _rx) = mpool_with_config(MpoolConfig::default());
+        let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+
+        let mut smsgs = vec![];
+        for sequence in 0..4 {
+            let smsg = create_smsg(&target, &sender, wallet.borrow_mut(), sequence, 1000000, 1);
+            mpool.push(smsg.clone()).await.unwrap();
+            smsgs.push(smsg);
+        }
+        // Only the message with sequence 1 has aged out.
+        let added = mpool.cur_tipset.lock().epoch();
+        mpool
+            .pending
+            .write()
+            .get_mut(&sender)
+            .unwrap()
+            .added_at
+            .insert(1, added - 10);
+
+        assert_eq!(
+            expire_messages(&mpool.pending, &mpool.local_msgs, added, 5),
+            3
+        );
+        assert_eq!(mpool.pending_for(&sender), Some(vec![smsgs[0].clone()]));
+        assert_eq!(mpool.get_sequence(&sender).unwrap(), 1);
+        let local: Vec<_> = mpool.local_msgs.read().iter().cloned().collect();
+        assert_eq!(local, vec![smsgs[0].clone()]);
+    }
+
+    #[tokio::test]
+    async fn test_push_rejects_invalid_messages() {
+        let (mpool, mut wallet, _rx) = mpool_with_config(MpoolConfig::default());
+        let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+
+        let mut sign_with_value = |value: TokenAmount| {
+            let umsg: Message = Message_v3 {
+                to: target.into(),
+                from: sender.into(),
+                value: value.into(),
+                gas_limit: 1000000,
+                gas_fee_cap: TokenAmount::from_atto(101).into(),
+                gas_premium: TokenAmount::from_atto(1).into(),
+                ..Message_v3::default()
+            }
+            .into();
+            let sig = wallet
+                .sign(&sender, umsg.cid().to_bytes().as_slice())
+                .unwrap();
+            SignedMessage::new_unchecked(umsg, sig)
+        };
+
+        // Negative value
+        let smsg = sign_with_value(TokenAmount::from_atto(-1));
+        let err = mpool.push(smsg).await.unwrap_err();
+        assert!(matches!(err, Error::InvalidMessage(_)), "{err}");
+
+        // Value overflowing the total supply
+        let smsg =
+            sign_with_value(crate::shim::econ::TOTAL_FILECOIN.clone() + TokenAmount::from_atto(1));
+        let err = mpool.push(smsg).await.unwrap_err();
+        assert!(matches!(err, Error::InvalidMessage(_)), "{err}");
+
+        // Gas limit below the cost of storing the message on chain
+        let smsg = create_smsg(&target, &sender, wallet.borrow_mut(), 0, 0, 1);
+        let err = mpool.push(smsg).await.unwrap_err();
+        assert!(matches!(err, Error::InvalidMessage(_)), "{err}");
+
+        // Signature over a different message
+        let signed = create_smsg(&target, &sender, wallet.borrow_mut(), 0, 1000000, 1);
+        let other = create_smsg(&target, &sender, wallet.borrow_mut(), 1, 1000000, 1);
+        let smsg =
+            SignedMessage::new_unchecked(other.message().clone(), signed.signature().clone());
+        let err = mpool.push(smsg).await.unwrap_err();
+        assert!(matches!(err, Error::InvalidSignature(_)), "{err}");
+
+        let (pending, _) = mpool.pending().unwrap();
+        assert!(pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_push_rejects_secp_signature_from_other_key() {
+        let (mpool, mut wallet, _rx) = mpool_with_config(MpoolConfig::default());
+        let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+
+        // Well-formed signature over the right message, but made with the
+        // key of `target` while declaring `sender` as the sender. The public
+        // key recovered from it derives a different f1 address.
+        let umsg: Message = Message_v3 {
+            to: target.into(),
+            from: sender.into(),
+            gas_limit: 1000000,
+            gas_fee_cap: TokenAmount::from_atto(101).into(),
+            gas_premium: TokenAmount::from_atto(1).into(),
+            ..Message_v3::default()
+        }
+        .into();
+        let sig = wallet
+            .sign(&target, umsg.cid().to_bytes().as_slice())
+            .unwrap();
+        let smsg = SignedMessage::new_unchecked(umsg, sig);
+        let err = mpool.push(smsg).await.unwrap_err();
+        assert!(matches!(err, Error::InvalidSignature(_)), "{err}");
+
+        assert!(mpool.pending_for(&sender).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_push_rejects_senders_outside_allowlist() {
+        let allowed = generate_key(SignatureType::Secp256k1).unwrap();
+        let config = MpoolConfig {
+            sender_allowlist: Some(vec![allowed.address]),
+            ..Default::default()
+        };
+        let (mpool, mut wallet, _rx) = mpool_with_config(config);
+        let allowed = wallet.import(allowed.key_info).unwrap();
+        let other = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+
+        let smsg = create_smsg(&target, &allowed, wallet.borrow_mut(), 0, 1000000, 1);
+        mpool.push(smsg).await.unwrap();
+
+        let smsg = create_smsg(&target, &other, wallet.borrow_mut(), 0, 1000000, 1);
+        let err = mpool.push(smsg).await.unwrap_err();
+        assert_eq!(err, Error::SenderNotAllowed(other.to_string()));
+        assert!(mpool.pending_for(&other).is_none());
+    }
+
+    #[tokio::test]
+    async fn test_push_allows_senders_by_id_address() {
+        let config = MpoolConfig {
+            sender_allowlist: Some(vec![Address::new_id(1000)]),
+            ..Default::default()
+        };
+        let (mpool, mut wallet, _rx) = mpool_with_config(config);
+        let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        mpool.api.set_id_address(&sender, Address::new_id(1000));
+
+        let smsg = create_smsg(&target, &sender, wallet.borrow_mut(), 0, 1000000, 1);
+        mpool.push(smsg).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_push_rejects_gas_fee_cap_below_minimum() {
+        let config = MpoolConfig {
+            min_gas_fee_cap: Some(TokenAmount::from_atto(1100)),
+            ..Default::default()
+        };
+        let (mpool, mut wallet, _rx) = mpool_with_config(config);
+        let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+
+        // `create_smsg` sets the fee cap to the premium plus 100.
+        let smsg = create_smsg(&target, &sender, wallet.borrow_mut(), 0, 1000000, 999);
+        assert_eq!(mpool.push(smsg).await.unwrap_err(), Error::GasFeeCapTooLow);
+        assert!(mpool.pending_for(&sender).is_none());
+
+        let smsg = create_smsg(&target, &sender, wallet.borrow_mut(), 0, 1000000, 1000);
+        mpool.push(smsg).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_push_rejects_submissions_when_saturated() {
+        let config = MpoolConfig {
+            max_pending_submissions: Some(1),
+            ..Default::default()
+        };
+        let (mpool, mut wallet, _rx) = mpool_with_config(config);
+        let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+
+        let smsgs = (0..2)
+            .map(|seq| create_smsg(&target, &sender, wallet.borrow_mut(), seq, 1000000, 1))
+            .collect::<Vec<_>>();
+
+        // The first submission fills the queue, and stays there until the
+        // worker gets to run.
+        let queued = mpool.push(smsgs[0].clone());
+        tokio::pin!(queued);
+        assert!(futures::poll!(&mut queued).is_pending());
+        assert_eq!(
+            mpool.push(smsgs[1].clone()).await.unwrap_err(),
+            Error::TooManySubmissions
+        );
+        assert!(mpool.pending_for(&sender).is_none());
+
+        assert_eq!(queued.await.unwrap(), smsgs[0].cid());
+        mpool.push(smsgs[1].clone()).await.unwrap();
+        assert_eq!(mpool.pending_for(&sender), Some(smsgs));
+    }
+
+    #[tokio::test]
+    async fn test_async_message_pool() {
+        let (mpool, mut wallet, _rx) = mpool_with_config(MpoolConfig::default());
+        let sender = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        let target = wallet.generate_addr(SignatureType::Secp256k1).unwrap();
+        mpool.api.set_state_sequence(&sender, 0);
 
         let mut smsg_vec = Vec::new();
         for i in 0..3 {
@@ -17,7 +17,8 @@ use crate::libp2p::{NetworkMessage, Topic, PUBSUB_MSG_STR};
 use crate::message::{valid_for_block_inclusion, ChainMessage, Message, SignedMessage};
 use crate::networks::{ChainConfig, NEWEST_NETWORK_VERSION};
 use crate::shim::{
-    address::Address,
+    address::{Address, Protocol},
+    clock::ChainEpoch,
     crypto::{Signature, SignatureType},
     econ::TokenAmount,
     gas::{price_list_by_network_version, Gas},
@@ -33,7 +34,7 @@ use lru::LruCache;
 use nonzero_ext::nonzero;
 use parking_lot::{Mutex, RwLock as SyncRwLock};
 use tokio::{sync::broadcast::error::RecvError, task::JoinSet, time::interval};
-use tracing::warn;
+use tracing::{debug, warn};
 
 use crate::message_pool::{
     config::MpoolConfig,
@@ -59,6 +60,9 @@ pub const MAX_UNTRUSTED_ACTOR_PENDING_MESSAGES: u64 = 10;
 #[derive(Clone, Default, Debug)]
 pub struct MsgSet {
     pub(in crate::message_pool) msgs: HashMap<u64, SignedMessage>,
+    /// Epoch at which each pending message entered the pool, keyed by
+    /// sequence.
+    pub(in crate::message_pool) added_at: HashMap<u64, ChainEpoch>,
     next_sequence: u64,
 }
 
@@ -68,6 +72,7 @@ impl MsgSet {
     pub fn new(sequence: u64) -> Self {
         MsgSet {
             msgs: HashMap::new(),
+            added_at: HashMap::new(),
             next_sequence: sequence,
         }
     }
@@ -75,25 +80,41 @@ impl MsgSet {
     /// Add a signed message to the `MsgSet`. Increase `next_sequence` if the
     /// message has a sequence greater than any existing message sequence.
     /// Use this method when pushing a message coming from trusted sources.
-    pub fn add_trusted<T>(&mut self, api: &T, m: SignedMessage) -> Result<(), Error>
+    pub fn add_trusted<T>(
+        &mut self,
+        api: &T,
+        m: SignedMessage,
+        epoch: ChainEpoch,
+    ) -> Result<(), Error>
     where
         T: Provider,
     {
-        self.add(api, m, true)
+        self.add(api, m, epoch, true)
     }
 
     /// Add a signed message to the `MsgSet`. Increase `next_sequence` if the
     /// message has a sequence greater than any existing message sequence.
     /// Use this method when pushing a message coming from untrusted sources.
     #[allow(dead_code)]
-    pub fn add_untrusted<T>(&mut self, api: &T, m: SignedMessage) -> Result<(), Error>
+    pub fn add_untrusted<T>(
+        &mut self,
+        api: &T,
+        m: SignedMessage,
+        epoch: ChainEpoch,
+    ) -> Result<(), Error>
     where
         T: Provider,
     {
-        self.add(api, m, false)
+        self.add(api, m, epoch, false)
     }
 
-    fn add<T>(&mut self, api: &T, m: SignedMessage, trusted: bool) -> Result<(), Error>
+    fn add<T>(
+        &mut self,
+        api: &T,
+        m: SignedMessage,
+        epoch: ChainEpoch,
+        trusted: bool,
+    ) -> Result<(), Error>
     where
         T: Provider,
     {
@@ -127,6 +148,7 @@ impl MsgSet {
                 trusted,
             ));
         }
+        self.added_at.insert(m.sequence(), epoch);
         if self.msgs.insert(m.sequence(), m).is_none() {
             metrics::MPOOL_MESSAGE_TOTAL.inc();
         }
@@ -136,6 +158,7 @@ impl MsgSet {
     /// Removes message with the given sequence. If applied, update the set's
     /// next sequence.
     pub fn rm(&mut self, sequence: u64, applied: bool) {
+        self.added_at.remove(&sequence);
         if self.msgs.remove(&sequence).is_none() {
             if applied && sequence >= self.next_sequence {
                 self.next_sequence = sequence + 1;
@@ -188,13 +211,19 @@ pub struct MessagePool<T> {
     /// Acts as a signal to republish messages from the republished set of
     /// messages
     pub repub_trigger: flume::Sender<()>,
-    local_msgs: Arc<SyncRwLock<HashSet<SignedMessage>>>,
+    pub(in crate::message_pool) local_msgs: Arc<SyncRwLock<HashSet<SignedMessage>>>,
     /// Configurable parameters of the message pool
     pub config: MpoolConfig,
     /// Chain configuration
     pub chain_config: Arc<ChainConfig>,
+    /// Queue of submissions waiting to be validated and added, bounded by
+    /// [`MpoolConfig::max_pending_submissions`]
+    submissions: Option<flume::Sender<Submission>>,
 }
 
+/// A pushed message along with the channel its outcome is reported on.
+type Submission = (SignedMessage, flume::Sender<Result<Cid, Error>>);
+
 impl<T> MessagePool<T>
 where
     T: Provider,
@@ -207,8 +236,30 @@ where
     }
 
     /// Push a signed message to the `MessagePool`. Additionally performs basic
-    /// checks on the validity of a message.
+    /// checks on the validity of a message. When submissions are queued, the
+    /// message is rejected with [`Error::TooManySubmissions`] if the queue is
+    /// full.
     pub async fn push(&self, msg: SignedMessage) -> Result<Cid, Error> {
+        let Some(submissions) = &self.submissions else {
+            return self.push_now(msg).await;
+        };
+        let (result_tx, result_rx) = flume::bounded(1);
+        submissions
+            .try_send((msg, result_tx))
+            .map_err(|e| match e {
+                flume::TrySendError::Full(_) => Error::TooManySubmissions,
+                flume::TrySendError::Disconnected(_) => {
+                    Error::Other("Message submission worker stopped".to_string())
+                }
+            })?;
+        result_rx
+            .recv_async()
+            .await
+            .map_err(|_| Error::Other("Message submission worker stopped".to_string()))?
+    }
+
+    /// Validates and adds a pushed message, publishing it if needed.
+    async fn push_now(&self, msg: SignedMessage) -> Result<Cid, Error> {
         self.check_message(&msg)?;
         let cid = msg.cid();
         let cur_ts = self.cur_tipset.lock().clone();
@@ -227,11 +278,17 @@ where
         Ok(cid)
     }
 
+    /// Static checks that don't need chain state: size, gas and value bounds,
+    /// fee cap floor and signature. Params are not decoded against the target
+    /// actor's methods: a message whose params fail to decode is still valid
+    /// for inclusion and only fails with an exit code when applied, as in
+    /// Lotus.
     fn check_message(&self, msg: &SignedMessage) -> Result<(), Error> {
         if to_vec(msg)?.len() > 32 * 1024 {
             return Err(Error::MessageTooBig);
         }
-        valid_for_block_inclusion(msg.message(), Gas::new(0), NEWEST_NETWORK_VERSION)?;
+        valid_for_block_inclusion(msg.message(), Gas::new(0), NEWEST_NETWORK_VERSION)
+            .map_err(|e| Error::InvalidMessage(e.to_string()))?;
         if msg.value() > *crate::shim::econ::TOTAL_FILECOIN {
             return Err(Error::MessageValueTooHigh);
         }
@@ -262,7 +319,7 @@ where
             return Ok(());
         }
 
-        msg.verify().map_err(Error::Other)?;
+        msg.verify().map_err(Error::InvalidSignature)?;
 
         self.sig_val_cache.lock().put(cid, ());
 
@@ -273,6 +330,11 @@ where
     /// given then call `add_locked` to finish adding the `signed_message`
     /// to pending.
     fn add_tipset(&self, msg: SignedMessage, cur_ts: &Tipset, local: bool) -> Result<bool, Error> {
+        if !self.is_sender_allowed(&msg.from(), cur_ts)? {
+            return Err(Error::SenderNotAllowed(msg.from().to_string()));
+        }
+        self.check_gas_fee_cap(&msg, cur_ts)?;
+
         let sequence = self.get_state_sequence(&msg.from(), cur_ts)?;
 
         if sequence > msg.message().sequence {
@@ -309,6 +371,38 @@ where
         Ok(publish)
     }
 
+    /// Check the `GasFeeCap` of `msg` against the configured minimum and,
+    /// if enabled, against the base fee of `cur_ts`.
+    fn check_gas_fee_cap(&self, msg: &SignedMessage, cur_ts: &Tipset) -> Result<(), Error> {
+        let gas_fee_cap = msg.gas_fee_cap();
+        if matches!(&self.config.min_gas_fee_cap, Some(min) if &gas_fee_cap < min) {
+            return Err(Error::GasFeeCapTooLow);
+        }
+        if self.config.reject_fee_cap_below_base_fee
+            && gas_fee_cap < cur_ts.block_headers().first().parent_base_fee
+        {
+            return Err(Error::GasFeeCapTooLow);
+        }
+        Ok(())
+    }
+
+    /// Check `from` against the configured sender allowlist. ID addresses in
+    /// the allowlist match senders whose key address resolves to them.
+    fn is_sender_allowed(&self, from: &Address, cur_ts: &Tipset) -> Result<bool, Error> {
+        let Some(allowlist) = &self.config.sender_allowlist else {
+            return Ok(true);
+        };
+        if allowlist.contains(from) {
+            return Ok(true);
+        }
+        if allowlist.iter().any(|addr| addr.protocol() == Protocol::ID) {
+            if let Some(id) = self.api.lookup_id(from, cur_ts)? {
+                return Ok(allowlist.contains(&id));
+            }
+        }
+        Ok(false)
+    }
+
     /// Finish verifying signed message before adding it to the pending `mset`
     /// hash-map. If an entry in the hash-map does not yet exist, create a
     /// new `mset` that will correspond to the from message and push it to
@@ -322,6 +416,7 @@ where
             self.pending.as_ref(),
             msg,
             self.get_state_sequence(&from, &cur_ts)?,
+            cur_ts.epoch(),
         )
     }
 
@@ -378,6 +473,24 @@ where
         Ok((out, cur_ts))
     }
 
+    /// Return the sequences missing from the pending messages of the given
+    /// sender, from its current state sequence up to its highest pending
+    /// sequence. Messages after a gap can't be selected until it is filled.
+    pub fn nonce_gaps(&self, a: &Address) -> Result<Vec<u64>, Error> {
+        let cur_ts = self.cur_tipset.lock().clone();
+        let state_sequence = self.get_state_sequence(a, &cur_ts)?;
+        let pending = self.pending.read();
+        let Some(mset) = pending.get(a) else {
+            return Ok(vec![]);
+        };
+        let Some(&max_sequence) = mset.msgs.keys().max() else {
+            return Ok(vec![]);
+        };
+        Ok((state_sequence..max_sequence)
+            .filter(|sequence| !mset.msgs.contains_key(sequence))
+            .collect())
+    }
+
     /// Return a Vector of signed messages for a given from address. This vector
     /// will be sorted by each `messsage`'s sequence. If no corresponding
     /// messages found, return None result type.
@@ -474,6 +587,14 @@ where
         let republished = Arc::new(SyncRwLock::new(HashSet::new()));
         let block_delay = chain_config.block_delay_secs;
 
+        let (submissions, submissions_rx) = match config.max_pending_submissions {
+            Some(max) => {
+                let (tx, rx) = flume::bounded::<Submission>(max);
+                (Some(tx), Some(rx))
+            }
+            None => (None, None),
+        };
+
         let (repub_trigger, repub_trigger_rx) = flume::bounded::<()>(4);
         let mut mp = MessagePool {
             local_addrs,
@@ -489,16 +610,48 @@ where
             network_sender,
             repub_trigger,
             chain_config: Arc::clone(&chain_config),
+            submissions,
         };
 
         mp.load_local()?;
 
+        if let Some(submissions_rx) = submissions_rx {
+            // Shares the pool state, but validates submissions in place
+            // instead of queueing them.
+            let worker = MessagePool {
+                local_addrs: mp.local_addrs.clone(),
+                pending: mp.pending.clone(),
+                cur_tipset: mp.cur_tipset.clone(),
+                api: mp.api.clone(),
+                network_name: mp.network_name.clone(),
+                network_sender: mp.network_sender.clone(),
+                bls_sig_cache: mp.bls_sig_cache.clone(),
+                sig_val_cache: mp.sig_val_cache.clone(),
+                republished: mp.republished.clone(),
+                repub_trigger: mp.repub_trigger.clone(),
+                local_msgs: mp.local_msgs.clone(),
+                config: mp.config.clone(),
+                chain_config: mp.chain_config.clone(),
+                submissions: None,
+            };
+            // Drains queued submissions
+            services.spawn(async move {
+                while let Ok((msg, result_tx)) = submissions_rx.recv_async().await {
+                    // The submitter may have gone away in the meantime
+                    let _ = result_tx.send(worker.push_now(msg).await);
+                }
+                Ok(())
+            });
+        }
+
         let mut subscriber = mp.api.subscribe_head_changes();
 
         let api = mp.api.clone();
         let bls_sig_cache = mp.bls_sig_cache.clone();
         let pending = mp.pending.clone();
         let republished = mp.republished.clone();
+        let local_msgs = mp.local_msgs.clone();
+        let max_message_age_epochs = mp.config.max_message_age_epochs;
 
         let cur_tipset = mp.cur_tipset.clone();
         let repub_trigger = Arc::new(mp.repub_trigger.clone());
@@ -527,6 +680,13 @@ where
                         )
                         .await
                         .context("Error changing head")?;
+                        if let Some(max_age) = max_message_age_epochs {
+                            let epoch = cur_tipset.lock().epoch();
+                            let expired = expire_messages(&pending, &local_msgs, epoch, max_age);
+                            if expired > 0 {
+                                debug!("Expired {expired} messages from the message pool");
+                            }
+                        }
                     }
                     Err(RecvError::Lagged(e)) => {
                         warn!("Head change subscriber lagged: skipping {} events", e);
@@ -587,6 +747,7 @@ pub(in crate::message_pool) fn add_helper<T>(
     pending: &SyncRwLock<HashMap<Address, MsgSet>>,
     msg: SignedMessage,
     sequence: u64,
+    epoch: ChainEpoch,
 ) -> Result<(), Error>
 where
     T: Provider,
@@ -607,11 +768,11 @@ where
     let mut pending = pending.write();
     let msett = pending.get_mut(&msg.from());
     match msett {
-        Some(mset) => mset.add_trusted(api, msg)?,
+        Some(mset) => mset.add_trusted(api, msg, epoch)?,
         None => {
             let mut mset = MsgSet::new(sequence);
             let from = msg.from();
-            mset.add_trusted(api, msg)?;
+            mset.add_trusted(api, msg, epoch)?;
             pending.insert(from, mset);
         }
     }
@@ -628,7 +789,8 @@ fn verify_msg_before_add(
     let epoch = cur_ts.epoch();
     let min_gas = price_list_by_network_version(chain_config.network_version(epoch))
         .on_chain_message(to_vec(m)?.len());
-    valid_for_block_inclusion(m.message(), min_gas.total(), NEWEST_NETWORK_VERSION)?;
+    valid_for_block_inclusion(m.message(), min_gas.total(), NEWEST_NETWORK_VERSION)
+        .map_err(|e| Error::InvalidMessage(e.to_string()))?;
     if !cur_ts.block_headers().is_empty() {
         let base_fee = &cur_ts.block_headers().first().parent_base_fee;
         let base_fee_lower_bound =
@@ -645,6 +807,48 @@ fn verify_msg_before_add(
     Ok(local)
 }
 
+/// Drop every pending message that entered the pool at least `max_age` epochs
+/// before `epoch`, along with the later messages of the same sender, which
+/// can't be included without it. Expired messages are also forgotten as local
+/// messages. Returns the number of messages removed.
+pub(in crate::message_pool) fn expire_messages(
+    pending: &SyncRwLock<HashMap<Address, MsgSet>>,
+    local_msgs: &SyncRwLock<HashSet<SignedMessage>>,
+    epoch: ChainEpoch,
+    max_age: ChainEpoch,
+) -> usize {
+    let mut pending = pending.write();
+    let mut expired = HashSet::new();
+    for (from, mset) in pending.iter_mut() {
+        let Some(first_expired) = mset
+            .added_at
+            .iter()
+            .filter(|(_, added)| epoch - **added >= max_age)
+            .map(|(sequence, _)| *sequence)
+            .min()
+        else {
+            continue;
+        };
+        let dropped: Vec<u64> = mset
+            .msgs
+            .keys()
+            .copied()
+            .filter(|sequence| *sequence >= first_expired)
+            .collect();
+        for sequence in dropped {
+            mset.rm(sequence, false);
+            expired.insert((*from, sequence));
+        }
+    }
+    pending.retain(|_, mset| !mset.msgs.is_empty());
+    if !expired.is_empty() {
+        local_msgs
+            .write()
+            .retain(|m| !expired.contains(&(m.from(), m.sequence())));
+    }
+    expired.len()
+}
+
 /// Remove a message from pending given the from address and sequence.
 pub fn remove(
     from: &Address,
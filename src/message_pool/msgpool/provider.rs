@@ -41,6 +41,9 @@ pub trait Provider {
     /// `StateTree` will be rooted at. Return `ActorState` or Error
     /// depending on whether or not `ActorState` is found
     fn get_actor_after(&self, addr: &Address, ts: &Tipset) -> Result<ActorState, Error>;
+    /// Look up the ID address of `addr` in the parent state of `ts`. Return
+    /// `None` if the address can't be resolved.
+    fn lookup_id(&self, addr: &Address, ts: &Tipset) -> Result<Option<Address>, Error>;
     /// Return the signed messages for given block header
     fn messages_for_block(
         &self,
@@ -104,6 +107,12 @@ where
         Ok(state.get_required_actor(addr)?)
     }
 
+    fn lookup_id(&self, addr: &Address, ts: &Tipset) -> Result<Option<Address>, Error> {
+        self.sm
+            .lookup_id(addr, ts)
+            .map_err(|e| Error::Other(e.to_string()))
+    }
+
     fn messages_for_block(
         &self,
         h: &CachingBlockHeader,
@@ -121,8 +130,13 @@ where
 
     fn chain_compute_base_fee(&self, ts: &Tipset) -> Result<TokenAmount, Error> {
         let smoke_height = self.sm.chain_config().epoch(Height::Smoke);
-        crate::chain::compute_base_fee(self.sm.blockstore(), ts, smoke_height)
-            .map_err(|err| err.into())
-            .map(Into::into)
+        crate::chain::compute_base_fee(
+            self.sm.blockstore(),
+            ts,
+            smoke_height,
+            self.sm.chain_config().min_base_fee.as_ref(),
+        )
+        .map_err(|err| err.into())
+        .map(Into::into)
     }
 }
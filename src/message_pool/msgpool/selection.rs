@@ -1056,6 +1056,63 @@ mod test_selection {
         }
     }
 
+    #[tokio::test]
+    async fn message_selection_priority_ignores_premiums() {
+        let db = MemoryDB::default();
+
+        let mut joinset = JoinSet::new();
+        let mut mpool = make_test_mpool(&mut joinset);
+
+        let ks1 = KeyStore::new(KeyStoreConfig::Memory).unwrap();
+        let mut w1 = Wallet::new(ks1);
+        let a1 = w1.generate_addr(SignatureType::Secp256k1).unwrap();
+
+        let ks2 = KeyStore::new(KeyStoreConfig::Memory).unwrap();
+        let mut w2 = Wallet::new(ks2);
+        let a2 = w2.generate_addr(SignatureType::Secp256k1).unwrap();
+
+        // set priority addrs to a1
+        let mut mpool_cfg = mpool.get_config().clone();
+        mpool_cfg.priority_addrs.push(a1);
+        mpool.set_config(&db, mpool_cfg).unwrap();
+
+        let b1 = mock_block(1, 1);
+        let ts = Tipset::from(&b1);
+        let api = &mpool.api.clone();
+        let bls_sig_cache = mpool.bls_sig_cache.clone();
+        let pending = mpool.pending.clone();
+        let cur_tipset = mpool.cur_tipset.clone();
+        let repub_trigger = Arc::new(mpool.repub_trigger.clone());
+        let republished = mpool.republished.clone();
+        head_change(
+            mpool.api.as_ref(),
+            bls_sig_cache.as_ref(),
+            repub_trigger.clone(),
+            republished.as_ref(),
+            pending.as_ref(),
+            cur_tipset.as_ref(),
+            Vec::new(),
+            vec![Tipset::from(b1)],
+        )
+        .await
+        .unwrap();
+
+        api.set_state_balance_raw(&a1, TokenAmount::from_whole(1));
+        api.set_state_balance_raw(&a2, TokenAmount::from_whole(1));
+
+        // a2 pays a hundred times the premium of the priority actor a1
+        let m = create_smsg(&a2, &a1, &mut w1, 0, TEST_GAS_LIMIT, 1);
+        mpool.add(m).unwrap();
+        let m = create_smsg(&a1, &a2, &mut w2, 0, TEST_GAS_LIMIT, 100);
+        mpool.add(m).unwrap();
+
+        let msgs = mpool.select_messages(&ts, 1.0).unwrap();
+
+        assert_eq!(msgs.len(), 2);
+        assert_eq!(msgs[0].from(), a1, "priority message should come first");
+        assert_eq!(msgs[1].from(), a2);
+    }
+
     #[tokio::test]
     async fn test_optimal_msg_selection1() {
         // this test uses just a single actor sending messages with a low tq
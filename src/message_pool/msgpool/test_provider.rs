@@ -34,6 +34,7 @@ pub struct TestApiInner {
     bmsgs: CidHashMap<Vec<SignedMessage>>,
     state_sequence: HashMap<Address, u64>,
     balances: HashMap<Address, TokenAmount>,
+    id_addresses: HashMap<Address, Address>,
     tipsets: Vec<Tipset>,
     max_actor_pending_messages: u64,
 }
@@ -75,6 +76,11 @@ impl TestApi {
         self.inner.lock().set_state_balance_raw(addr, bal)
     }
 
+    /// Set the ID address that `addr` resolves to for `TestApi`
+    pub fn set_id_address(&self, addr: &Address, id: Address) {
+        self.inner.lock().id_addresses.insert(*addr, id);
+    }
+
     /// Set the block messages for `TestApi`
     pub fn set_block_messages(&self, h: &CachingBlockHeader, msgs: Vec<SignedMessage>) {
         self.inner.lock().set_block_messages(h, msgs)
@@ -171,6 +177,10 @@ impl Provider for TestApi {
         Ok(actor)
     }
 
+    fn lookup_id(&self, addr: &Address, _ts: &Tipset) -> Result<Option<Address>, Error> {
+        Ok(self.inner.lock().id_addresses.get(addr).copied())
+    }
+
     fn messages_for_block(
         &self,
         h: &CachingBlockHeader,
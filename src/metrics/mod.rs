@@ -162,6 +162,8 @@ pub mod values {
     pub const TIPSET: KindLabel = KindLabel::new("tipset");
     /// tipset cache in state manager
     pub const STATE_MANAGER_TIPSET: KindLabel = KindLabel::new("sm_tipset");
+    /// VM circulating supply cache in state manager
+    pub const STATE_MANAGER_CIRC_SUPPLY: KindLabel = KindLabel::new("sm_circ_supply");
 }
 
 pub fn default_histogram() -> Histogram {
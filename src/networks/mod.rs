@@ -4,19 +4,21 @@
 use std::str::FromStr;
 
 use ahash::HashMap;
+use anyhow::Context as _;
 use cid::Cid;
 use fil_actors_shared::v13::runtime::Policy;
 use itertools::Itertools;
 use libp2p::Multiaddr;
 use once_cell::sync::Lazy;
 use serde::{Deserialize, Serialize};
-use strum_macros::Display;
+use strum_macros::{Display, EnumString};
 use tracing::warn;
 
 use crate::beacon::{BeaconPoint, BeaconSchedule, DrandBeacon, DrandConfig};
 use crate::db::SettingsStore;
 use crate::eth::EthChainId;
 use crate::shim::clock::{ChainEpoch, EPOCH_DURATION_SECONDS};
+use crate::shim::econ::TokenAmount;
 use crate::shim::sector::{RegisteredPoStProofV3, RegisteredSealProofV3};
 use crate::shim::version::NetworkVersion;
 use crate::utils::misc::env::env_or_default;
@@ -42,6 +44,9 @@ pub const NEWEST_NETWORK_VERSION: NetworkVersion = NetworkVersion::V17;
 
 const ENV_FOREST_BLOCK_DELAY_SECS: &str = "FOREST_BLOCK_DELAY_SECS";
 const ENV_FOREST_PROPAGATION_DELAY_SECS: &str = "FOREST_PROPAGATION_DELAY_SECS";
+const ENV_FOREST_MIN_BASE_FEE: &str = "FOREST_MIN_BASE_FEE";
+const ENV_FOREST_UPGRADE_SCHEDULE: &str = "FOREST_UPGRADE_SCHEDULE";
+const ENV_FOREST_GENESIS_CID: &str = "FOREST_GENESIS_CID";
 
 /// Forest builtin `filecoin` network chains. In general only `mainnet` and its
 /// chain information should be considered stable.
@@ -99,6 +104,18 @@ impl NetworkChain {
         Self::from_genesis(cid).unwrap_or(Self::Devnet(String::from("devnet")))
     }
 
+    /// Maps the protocol-level network name stored in the init actor to a
+    /// builtin [`NetworkChain`]. Returns `None` for any other name, such as
+    /// the name of a devnet.
+    pub fn from_network_name(name: &str) -> Option<Self> {
+        match name {
+            "testnetnet" => Some(Self::Mainnet),
+            "calibrationnet" => Some(Self::Calibnet),
+            "butterflynet" => Some(Self::Butterflynet),
+            _ => None,
+        }
+    }
+
     pub fn is_testnet(&self) -> bool {
         !matches!(self, NetworkChain::Mainnet)
     }
@@ -109,7 +126,7 @@ impl NetworkChain {
 }
 
 /// Defines the meaningful heights of the protocol.
-#[derive(Debug, Display, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[derive(Debug, Display, EnumString, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
 #[cfg_attr(test, derive(derive_quickcheck_arbitrary::Arbitrary))]
 pub enum Height {
     Breeze,
@@ -223,6 +240,9 @@ pub struct ChainConfig {
     pub policy: Policy,
     pub eth_chain_id: EthChainId,
     pub breeze_gas_tamping_duration: i64,
+    /// Optional floor for the base fee. When set, the computed base fee is never
+    /// lower than this value. Block producers and validators must agree on it.
+    pub min_base_fee: Option<TokenAmount>,
 }
 
 impl ChainConfig {
@@ -242,6 +262,7 @@ impl ChainConfig {
             policy: make_mainnet_policy!(v13),
             eth_chain_id: ETH_CHAIN_ID,
             breeze_gas_tamping_duration: BREEZE_GAS_TAMPING_DURATION,
+            min_base_fee: None,
         }
     }
 
@@ -261,6 +282,7 @@ impl ChainConfig {
             policy: make_calibnet_policy!(v13),
             eth_chain_id: ETH_CHAIN_ID,
             breeze_gas_tamping_duration: BREEZE_GAS_TAMPING_DURATION,
+            min_base_fee: None,
         }
     }
 
@@ -268,7 +290,7 @@ impl ChainConfig {
         use devnet::*;
         Self {
             network: NetworkChain::Devnet("devnet".to_string()),
-            genesis_cid: None,
+            genesis_cid: std::env::var(ENV_FOREST_GENESIS_CID).ok(),
             bootstrap_peers: Vec::new(),
             block_delay_secs: env_or_default(ENV_FOREST_BLOCK_DELAY_SECS, 4),
             propagation_delay_secs: env_or_default(ENV_FOREST_PROPAGATION_DELAY_SECS, 1),
@@ -277,6 +299,7 @@ impl ChainConfig {
             policy: make_devnet_policy!(v13),
             eth_chain_id: ETH_CHAIN_ID,
             breeze_gas_tamping_duration: BREEZE_GAS_TAMPING_DURATION,
+            min_base_fee: get_min_base_fee_from_env(ENV_FOREST_MIN_BASE_FEE),
         }
     }
 
@@ -297,6 +320,7 @@ impl ChainConfig {
             policy: make_butterfly_policy!(v13),
             eth_chain_id: ETH_CHAIN_ID,
             breeze_gas_tamping_duration: BREEZE_GAS_TAMPING_DURATION,
+            min_base_fee: None,
         }
     }
 
@@ -362,6 +386,55 @@ impl ChainConfig {
             .unwrap_or(0)
     }
 
+    /// Applies the custom upgrade schedule from `FOREST_UPGRADE_SCHEDULE`, if set.
+    /// Only devnets can override their schedule.
+    pub fn with_upgrade_schedule_from_env(self) -> anyhow::Result<Self> {
+        match get_upgrade_schedule_from_env(ENV_FOREST_UPGRADE_SCHEDULE)? {
+            Some(schedule) => {
+                anyhow::ensure!(
+                    self.is_devnet(),
+                    "{ENV_FOREST_UPGRADE_SCHEDULE} is only supported on devnets"
+                );
+                self.with_upgrade_schedule(&schedule)
+            }
+            None => Ok(self),
+        }
+    }
+
+    /// Replaces the epochs of the given upgrades with custom ones, keeping their
+    /// actor bundles. Upgrades missing from the schedule keep their built-in
+    /// epochs. Fails if the resulting schedule is not monotonic.
+    pub fn with_upgrade_schedule(
+        mut self,
+        schedule: &HashMap<Height, ChainEpoch>,
+    ) -> anyhow::Result<Self> {
+        for (height, epoch) in schedule {
+            self.height_infos.entry(*height).or_default().epoch = *epoch;
+        }
+        self.validate_upgrade_schedule()?;
+        Ok(self)
+    }
+
+    /// Checks that the network version never decreases along the upgrade
+    /// schedule. Negative epochs denote upgrades already active at genesis and
+    /// [`ChainEpoch::MAX`] denotes disabled ones; both are ignored.
+    pub fn validate_upgrade_schedule(&self) -> anyhow::Result<()> {
+        let scheduled = self
+            .height_infos
+            .iter()
+            .filter(|(_, info)| (0..ChainEpoch::MAX).contains(&info.epoch))
+            .sorted_by_key(|(height, info)| (info.epoch, NetworkVersion::from(**height)));
+        for ((prev, prev_info), (next, next_info)) in scheduled.tuple_windows() {
+            anyhow::ensure!(
+                NetworkVersion::from(*prev) <= NetworkVersion::from(*next),
+                "upgrade {prev} at epoch {} is scheduled after {next} at epoch {}",
+                prev_info.epoch,
+                next_info.epoch
+            );
+        }
+        Ok(())
+    }
+
     pub async fn genesis_bytes<DB: SettingsStore>(
         &self,
         db: &DB,
@@ -429,6 +502,43 @@ fn get_upgrade_height_from_env(env_var_key: &str) -> Option<ChainEpoch> {
     None
 }
 
+fn get_min_base_fee_from_env(env_var_key: &str) -> Option<TokenAmount> {
+    if let Ok(value) = std::env::var(env_var_key) {
+        if let Ok(atto) = value.parse::<u64>() {
+            return Some(TokenAmount::from_atto(atto));
+        } else {
+            warn!("Failed to parse {env_var_key}={value}, value should be an integer (in attoFIL)");
+        }
+    }
+    None
+}
+
+/// Parses a custom upgrade schedule such as `Dragon=100,Waffle=200` from the
+/// given environment variable.
+fn get_upgrade_schedule_from_env(
+    env_var_key: &str,
+) -> anyhow::Result<Option<HashMap<Height, ChainEpoch>>> {
+    let Ok(value) = std::env::var(env_var_key) else {
+        return Ok(None);
+    };
+    value
+        .split(',')
+        .map(|entry| {
+            let (height, epoch) = entry
+                .split_once('=')
+                .with_context(|| format!("invalid {env_var_key} entry `{entry}`"))?;
+            let height = Height::from_str(height.trim())
+                .with_context(|| format!("unknown upgrade `{height}` in {env_var_key}"))?;
+            let epoch = epoch
+                .trim()
+                .parse()
+                .with_context(|| format!("invalid epoch `{epoch}` in {env_var_key}"))?;
+            Ok((height, epoch))
+        })
+        .collect::<anyhow::Result<_>>()
+        .map(Some)
+}
+
 #[macro_export]
 macro_rules! make_height {
     ($id:ident,$epoch:expr) => {
@@ -469,6 +579,23 @@ pub fn calculate_expected_epoch(
 mod tests {
     use super::*;
 
+    #[test]
+    fn network_names_of_builtin_networks() {
+        assert_eq!(
+            NetworkChain::from_network_name("testnetnet"),
+            Some(NetworkChain::Mainnet)
+        );
+        assert_eq!(
+            NetworkChain::from_network_name("calibrationnet"),
+            Some(NetworkChain::Calibnet)
+        );
+        assert_eq!(
+            NetworkChain::from_network_name("butterflynet"),
+            Some(NetworkChain::Butterflynet)
+        );
+        assert_eq!(NetworkChain::from_network_name("localnet"), None);
+    }
+
     fn heights_are_present(height_infos: &HashMap<Height, HeightInfo>) {
         /// These are required heights that need to be defined for all networks, for, e.g., conformance
         /// with `Filecoin.StateGetNetworkParams` RPC method.
@@ -547,6 +674,20 @@ mod tests {
         assert_eq!(epoch, None);
     }
 
+    #[test]
+    fn test_min_base_fee_valid_env_var() {
+        std::env::set_var("FOREST_TEST_VAR_4", "1000");
+        let fee = get_min_base_fee_from_env("FOREST_TEST_VAR_4");
+        assert_eq!(fee, Some(TokenAmount::from_atto(1000)));
+    }
+
+    #[test]
+    fn test_min_base_fee_invalid_env_var() {
+        std::env::set_var("FOREST_TEST_VAR_5", "foo");
+        let fee = get_min_base_fee_from_env("FOREST_TEST_VAR_5");
+        assert_eq!(fee, None);
+    }
+
     #[test]
     fn test_calculate_expected_epoch() {
         // now, genesis, block_delay
@@ -580,6 +721,57 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_builtin_upgrade_schedules_are_monotonic() {
+        for config in [
+            ChainConfig::mainnet(),
+            ChainConfig::calibnet(),
+            ChainConfig::butterflynet(),
+            ChainConfig::devnet(),
+        ] {
+            config.validate_upgrade_schedule().unwrap();
+        }
+    }
+
+    #[test]
+    fn test_custom_upgrade_schedule() {
+        let schedule = HashMap::from_iter([
+            (Height::Dragon, 10),
+            (Height::Phoenix, 50),
+            (Height::Waffle, 100),
+        ]);
+        let config = ChainConfig::devnet()
+            .with_upgrade_schedule(&schedule)
+            .unwrap();
+        assert_eq!(config.epoch(Height::Waffle), 100);
+        assert_eq!(config.network_version(100), NetworkVersion::V22);
+        assert_eq!(config.network_version(101), NetworkVersion::V23);
+
+        // Waffle before Phoenix
+        let schedule = HashMap::from_iter([(Height::Phoenix, 50), (Height::Waffle, 40)]);
+        assert!(ChainConfig::devnet()
+            .with_upgrade_schedule(&schedule)
+            .is_err());
+    }
+
+    #[test]
+    fn test_upgrade_schedule_env_var() {
+        std::env::set_var("FOREST_TEST_VAR_6", "Phoenix=50, Waffle=100");
+        let schedule = get_upgrade_schedule_from_env("FOREST_TEST_VAR_6")
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            schedule,
+            HashMap::from_iter([(Height::Phoenix, 50), (Height::Waffle, 100)])
+        );
+
+        std::env::set_var("FOREST_TEST_VAR_7", "Pineapple=50");
+        assert!(get_upgrade_schedule_from_env("FOREST_TEST_VAR_7").is_err());
+        assert!(get_upgrade_schedule_from_env("FOREST_TEST_VAR_8")
+            .unwrap()
+            .is_none());
+    }
+
     #[test]
     fn network_chain_display() {
         assert_eq!(NetworkChain::Mainnet.to_string(), "mainnet");
@@ -28,6 +28,7 @@ use cid::Cid;
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::{CborStore, RawBytes};
 use hex::ToHex;
+use itertools::Itertools as _;
 use jsonrpsee::types::error::ErrorObjectOwned;
 use jsonrpsee::types::Params;
 use libipld::Ipld;
@@ -43,6 +44,7 @@ use tokio::sync::{
     broadcast::{self, Receiver as Subscriber},
     Mutex,
 };
+use tracing::warn;
 
 pub enum ChainGetMessage {}
 impl RpcMethod<1> for ChainGetMessage {
@@ -627,15 +629,92 @@ impl RpcMethod<1> for ChainTipSetWeight {
     }
 }
 
+/// Checks the basic chain invariants between the `from` tipset and its
+/// descendant `to`, which defaults to the heaviest tipset. Returns the
+/// description of every violation found. The whole range is walked, so this
+/// is restricted to admins.
+pub enum ChainCheckInvariants {}
+impl RpcMethod<2> for ChainCheckInvariants {
+    const NAME: &'static str = "Forest.ChainCheckInvariants";
+    const PARAM_NAMES: [&'static str; 2] = ["from", "to"];
+    const API_PATHS: ApiPaths = ApiPaths::V0;
+    const PERMISSION: Permission = Permission::Admin;
+
+    type Params = (TipsetKey, ApiTipsetKey);
+    type Ok = Vec<String>;
+
+    async fn handle(
+        ctx: Ctx<impl Blockstore>,
+        (from, ApiTipsetKey(to)): Self::Params,
+    ) -> Result<Self::Ok, ServerError> {
+        let from = ctx.chain_index().load_required_tipset(&from)?;
+        let to = ctx.chain_store().load_required_tipset_or_heaviest(&to)?;
+        let violations = crate::chain::check_chain_invariants(ctx.chain_store(), &from, to)?;
+        Ok(violations.iter().map(ToString::to_string).collect())
+    }
+}
+
+/// Runs the garbage collector right away. The first run after startup or after
+/// a sweep only marks the database, as no records are old enough to be swept
+/// yet, and reports [`ChainGCResult::swept`] as `false`.
+pub enum ChainGC {}
+impl RpcMethod<0> for ChainGC {
+    const NAME: &'static str = "Forest.ChainGC";
+    const PARAM_NAMES: [&'static str; 0] = [];
+    const API_PATHS: ApiPaths = ApiPaths::V0;
+    const PERMISSION: Permission = Permission::Admin;
+
+    type Params = ();
+    type Ok = ChainGCResult;
+
+    async fn handle(ctx: Ctx<impl Blockstore>, (): Self::Params) -> Result<Self::Ok, ServerError> {
+        let gc_requests = ctx
+            .gc_requests
+            .as_ref()
+            .context("garbage collection is disabled")?;
+        let (result_send, result_recv) = flume::bounded(1);
+        gc_requests
+            .send_async(result_send)
+            .await
+            .context("garbage collector is not running")?;
+        let deleted = result_recv
+            .recv_async()
+            .await
+            .context("garbage collector is not running")??;
+        Ok(ChainGCResult {
+            deleted: deleted.unwrap_or_default(),
+            swept: deleted.is_some(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "PascalCase")]
+pub struct ChainGCResult {
+    /// Number of deleted records.
+    pub deleted: u32,
+    /// Whether the database was swept, rather than only marked.
+    pub swept: bool,
+}
+lotus_json_with_self!(ChainGCResult);
+
 pub const CHAIN_NOTIFY: &str = "Filecoin.ChainNotify";
-pub(crate) fn chain_notify<DB: Blockstore>(
+pub(crate) fn chain_notify<DB: Blockstore + Send + Sync + 'static>(
     _params: Params<'_>,
     data: &crate::rpc::RPCState<DB>,
+) -> Subscriber<Vec<ApiHeadChange>> {
+    notify_head_changes(data.chain_store().clone())
+}
+
+/// Forwards the head changes of `chain_store`, reverting abandoned forks on
+/// reorgs, after sending the current head.
+fn notify_head_changes<DB: Blockstore + Send + Sync + 'static>(
+    chain_store: Arc<ChainStore<DB>>,
 ) -> Subscriber<Vec<ApiHeadChange>> {
     let (sender, receiver) = broadcast::channel(100);
 
     // As soon as the channel is created, send the current tipset
-    let current = data.chain_store().heaviest_tipset();
+    let current = chain_store.heaviest_tipset();
     let (change, tipset) = ("current".into(), current);
     sender
         .send(vec![ApiHeadChange {
@@ -644,24 +723,45 @@ pub(crate) fn chain_notify<DB: Blockstore>(
         }])
         .expect("receiver is not dropped");
 
-    let mut subscriber = data.chain_store().publisher().subscribe();
+    let mut subscriber = chain_store.publisher().subscribe();
 
     tokio::spawn(async move {
-        // Skip first message
-        let _ = subscriber.recv().await;
+        // Skip first message, only tracking it as the current head
+        let mut head = match subscriber.recv().await {
+            Ok(HeadChange::Apply(ts)) => ts,
+            Err(_) => tipset,
+        };
 
         while let Ok(v) = subscriber.recv().await {
-            let (change, tipset) = match v {
-                HeadChange::Apply(ts) => ("apply".into(), ts),
+            let HeadChange::Apply(ts) = v;
+
+            // Emit reverts for the abandoned fork if the new head doesn't
+            // extend the previous one.
+            let (reverts, applies) = if ts.parents() == head.key() {
+                (vec![], vec![ts.clone()])
+            } else {
+                chain_store
+                    .reorg_ops(head.clone(), ts.clone())
+                    .unwrap_or_else(|e| {
+                        warn!("failed to compute reorg for {}: {e}", ts.key());
+                        (vec![], vec![ts.clone()])
+                    })
             };
-
-            if sender
-                .send(vec![ApiHeadChange {
-                    change,
+            head = ts;
+
+            let changes = reverts
+                .into_iter()
+                .map(|ts| ("revert", ts))
+                .chain(applies.into_iter().map(|ts| ("apply", ts)))
+                .map(|(change, tipset)| ApiHeadChange {
+                    change: change.into(),
                     tipset: tipset.as_ref().clone(),
-                }])
-                .is_err()
-            {
+                })
+                .collect_vec();
+            if changes.is_empty() {
+                continue;
+            }
+            if sender.send(changes).is_err() {
                 break;
             }
         }
@@ -864,9 +964,15 @@ mod tests {
 
     use crate::{
         blocks::{chain4u, Chain4U, RawBlockHeader},
-        db::{car::PlainCar, MemoryDB},
+        chain_sync::SyncConfig,
+        db::{car::PlainCar, MarkAndSweep, MemoryDB},
+        key_management::{KeyStore, KeyStoreConfig},
+        message_pool::test_provider::{mock_block, mock_block_with_parents},
         networks::{self, ChainConfig},
+        state_manager::StateManager,
+        test_utils::{chain_store_with, rpc_ctx},
     };
+    use std::time::Duration;
 
     #[test]
     fn revert_to_ancestor_linear() {
@@ -976,6 +1082,118 @@ mod tests {
         let _ = (a, c1);
     }
 
+    #[tokio::test]
+    async fn chain_notify_reverts_abandoned_fork() {
+        let store = Arc::new(ChainStore::calibnet());
+        chain4u! {
+            in store.blockstore();
+            [genesis = store.genesis_block_header()]
+            -> [a] -> [b1]
+        };
+        chain4u! {
+            from [a] in store.blockstore();
+            [b2] -> [c2]
+        };
+        let mut head_changes = notify_head_changes(store.clone());
+        async fn next(head_changes: &mut Subscriber<Vec<ApiHeadChange>>) -> Vec<(String, Tipset)> {
+            let changes = head_changes.recv().await.unwrap();
+            changes
+                .into_iter()
+                .map(|ApiHeadChange { change, tipset }| (change, tipset))
+                .collect_vec()
+        }
+        let change = |change: &str, it: &RawBlockHeader| (change.to_string(), it.make_tipset());
+
+        assert_eq!(next(&mut head_changes).await, [change("current", genesis)]);
+        // The first head change only sets the head to compare against.
+        for it in [a, b1, c2] {
+            store
+                .set_heaviest_tipset(Arc::new(it.make_tipset()))
+                .unwrap();
+        }
+        assert_eq!(next(&mut head_changes).await, [change("apply", b1)]);
+        assert_eq!(
+            next(&mut head_changes).await,
+            [
+                change("revert", b1),
+                change("apply", b2),
+                change("apply", c2)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn chain_gc_reports_mark_only_runs() {
+        const DEPTH: ChainEpoch = 5;
+        const UNREACHABLE: u64 = 3;
+
+        let db = Arc::new(MemoryDB::default());
+        // The beacon schedule requires a genesis timestamp.
+        let genesis = CachingBlockHeader::new(RawBlockHeader {
+            timestamp: 1,
+            ..mock_block(1, 1).into_raw()
+        });
+        let store = chain_store_with(db.clone(), &[Tipset::from(genesis)]).await;
+        let run_epochs = |delta: ChainEpoch| {
+            for _ in 0..delta {
+                let block = mock_block_with_parents(&store.heaviest_tipset(), 1, 1);
+                db.put_cbor_default(&block).unwrap();
+                store.set_heaviest_tipset(Arc::new(block.into())).unwrap();
+            }
+        };
+        run_epochs(DEPTH);
+        for idx in 0..UNREACHABLE {
+            let block: CachingBlockHeader = mock_block(1 + idx, 1 + UNREACHABLE);
+            db.put_cbor_default(&block).unwrap();
+        }
+
+        // Long waits keep the scheduled runs from getting in the way of the
+        // requested ones.
+        let wait = Duration::from_secs(3600);
+        let (gc_send, gc_recv) = flume::unbounded();
+        let mut gc = MarkAndSweep::new(
+            db.clone(),
+            Box::new({
+                let store = store.clone();
+                move || store.heaviest_tipset()
+            }),
+            DEPTH,
+            wait,
+        );
+        tokio::spawn(async move { gc.gc_loop(wait, gc_recv).await });
+
+        let state_manager = Arc::new(
+            StateManager::new(
+                store.clone(),
+                store.chain_config().clone(),
+                Arc::new(SyncConfig::default()),
+            )
+            .unwrap(),
+        );
+        let ctx = rpc_ctx(
+            state_manager,
+            KeyStore::new(KeyStoreConfig::Memory).unwrap(),
+            Some(gc_send),
+        );
+
+        // The first run only marks the database.
+        assert_eq!(
+            ChainGC::handle(ctx.clone(), ()).await.unwrap(),
+            ChainGCResult {
+                deleted: 0,
+                swept: false
+            }
+        );
+        run_epochs(DEPTH);
+        assert_eq!(
+            ChainGC::handle(ctx, ()).await.unwrap(),
+            ChainGCResult {
+                deleted: UNREACHABLE as u32,
+                swept: true
+            }
+        );
+    }
+
     impl ChainStore<Chain4U<PlainCar<&'static [u8]>>> {
         fn _load(genesis_car: &'static [u8], genesis_cid: Cid) -> Self {
             let db = Arc::new(Chain4U::with_blockstore(
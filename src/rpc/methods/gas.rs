@@ -4,7 +4,7 @@
 use std::sync::Arc;
 
 use crate::blocks::Tipset;
-use crate::chain::{BASE_FEE_MAX_CHANGE_DENOM, BLOCK_GAS_TARGET};
+use crate::chain::BASE_FEE_MAX_CHANGE_DENOM;
 use crate::interpreter::VMTrace;
 use crate::message::{ChainMessage, Message as MessageTrait, SignedMessage};
 use crate::rpc::{error::ServerError, types::*, ApiPaths, Ctx, Permission, RpcMethod};
@@ -23,8 +23,6 @@ use rand_distr::{Distribution, Normal};
 
 use super::state::InvocResult;
 
-const MIN_GAS_PREMIUM: f64 = 100000.0;
-
 /// Estimate the fee cap
 pub enum GasEstimateFeeCap {}
 impl RpcMethod<3> for GasEstimateFeeCap {
@@ -85,69 +83,18 @@ impl RpcMethod<4> for GasEstimateGasPremium {
     }
 }
 
+/// Estimates the gas premium for inclusion within `nblocksincl` epochs from
+/// the last `2 * nblocksincl` tipsets, as Lotus does, and scales it by a small
+/// random factor so that concurrent estimates don't all land on one price.
 pub async fn estimate_gas_premium<DB: Blockstore>(
     data: &Ctx<DB>,
-    mut nblocksincl: u64,
+    nblocksincl: u64,
 ) -> Result<TokenAmount, ServerError> {
-    if nblocksincl == 0 {
-        nblocksincl = 1;
-    }
-
-    struct GasMeta {
-        pub price: TokenAmount,
-        pub limit: u64,
-    }
-
-    let mut prices: Vec<GasMeta> = Vec::new();
-    let mut blocks = 0;
-
-    let mut ts = data.chain_store().heaviest_tipset();
-
-    for _ in 0..(nblocksincl * 2) {
-        if ts.epoch() == 0 {
-            break;
-        }
-        let pts = data.chain_index().load_required_tipset(ts.parents())?;
-        blocks += pts.block_headers().len();
-        let msgs = crate::chain::messages_for_tipset(data.store_owned(), &pts)?;
-
-        prices.append(
-            &mut msgs
-                .iter()
-                .map(|msg| GasMeta {
-                    price: msg.message().gas_premium(),
-                    limit: msg.message().gas_limit(),
-                })
-                .collect(),
-        );
-        ts = pts;
-    }
-
-    prices.sort_by(|a, b| b.price.cmp(&a.price));
-    let mut at = BLOCK_GAS_TARGET * blocks as u64 / 2;
-    let mut prev = TokenAmount::zero();
-    let mut premium = TokenAmount::zero();
-
-    for price in prices {
-        at -= price.limit;
-        if at > 0 {
-            prev = price.price;
-            continue;
-        }
-        if prev == TokenAmount::zero() {
-            let ret: TokenAmount = price.price + TokenAmount::from_atto(1);
-            return Ok(ret);
-        }
-        premium = (&price.price + &prev).div_floor(2) + TokenAmount::from_atto(1)
-    }
-
-    if premium == TokenAmount::zero() {
-        premium = TokenAmount::from_atto(match nblocksincl {
-            1 => (MIN_GAS_PREMIUM * 2.0) as u64,
-            2 => (MIN_GAS_PREMIUM * 1.5) as u64,
-            _ => MIN_GAS_PREMIUM as u64,
-        });
-    }
+    let nblocksincl = nblocksincl.max(1);
+    // The walk back stops at genesis, so large values only cost a full scan.
+    let mut premium = data
+        .state_manager
+        .estimate_gas_premium(nblocksincl.saturating_mul(2), nblocksincl)?;
 
     let precision = 32;
 
@@ -157,7 +104,8 @@ pub async fn estimate_gas_premium<DB: Blockstore>(
         .sample(&mut rand::thread_rng());
 
     premium *= BigInt::from_f64(noise * (1i64 << precision) as f64)
-        .context("failed to convert gas premium f64 to bigint")?;
+        .context("failed to convert gas premium f64 to bigint")?
+        + 1;
     premium = premium.div_floor(1i64 << precision);
 
     Ok(premium)
@@ -6,6 +6,7 @@ use crate::blocks::{CachingBlockHeader, Ticket, TipsetKey};
 use crate::blocks::{ElectionProof, RawBlockHeader};
 
 use crate::chain::{compute_base_fee, ChainStore};
+use crate::chain_sync::{block_size_checks, block_timestamp_checks};
 
 use crate::fil_cns::weight;
 use crate::key_management::{Key, KeyStore};
@@ -39,8 +40,10 @@ use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_tuple::Serialize_tuple;
 use tokio::sync::RwLock;
+use tracing::{info, warn};
 
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Eq, PartialEq, JsonSchema)]
 #[serde(rename_all = "PascalCase")]
@@ -90,6 +93,42 @@ pub struct BlockMessage {
 
 lotus_json_with_self!(BlockMessage);
 
+/// Time budget of a block build, checked at the end of each of its phases.
+struct BuildBudget {
+    epoch: ChainEpoch,
+    limit: Option<Duration>,
+    start: Instant,
+    phase_start: Instant,
+}
+
+impl BuildBudget {
+    fn new(epoch: ChainEpoch, limit: Option<Duration>) -> Self {
+        let now = Instant::now();
+        Self {
+            epoch,
+            limit,
+            start: now,
+            phase_start: now,
+        }
+    }
+
+    /// Fails if the build has run over budget by the end of `phase`.
+    fn check(&mut self, phase: &str) -> anyhow::Result<()> {
+        let phase_time = self.phase_start.elapsed();
+        self.phase_start = Instant::now();
+        match self.limit {
+            Some(limit) if self.start.elapsed() > limit => {
+                warn!(
+                    "Aborting block creation at epoch {}: the {phase} took {phase_time:?}, running over the {limit:?} budget",
+                    self.epoch
+                );
+                anyhow::bail!("block creation ran over its {limit:?} budget during the {phase}")
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
 #[derive(Serialize_tuple)]
 struct MessageMeta {
     bls_messages: Cid,
@@ -110,6 +149,13 @@ impl RpcMethod<1> for MinerCreateBlock {
         ctx: Ctx<impl Blockstore + Send + Sync + 'static>,
         (block_template,): Self::Params,
     ) -> Result<Self::Ok, ServerError> {
+        let mut budget = BuildBudget::new(
+            block_template.epoch,
+            ctx.state_manager
+                .sync_config()
+                .max_block_build_time_ms
+                .map(Duration::from_millis),
+        );
         let store = ctx.store();
         let parent_tipset = ctx
             .chain_index()
@@ -137,8 +183,10 @@ impl RpcMethod<1> for MinerCreateBlock {
                 .get(&Height::Smoke)
                 .context("Missing Smoke height")?
                 .epoch,
+            ctx.chain_config().min_base_fee.as_ref(),
         )?;
         let (state, receipts) = ctx.state_manager.tipset_state(&parent_tipset).await?;
+        budget.check("state read")?;
 
         let network_version = ctx.state_manager.get_network_version(block_template.epoch);
 
@@ -189,6 +237,7 @@ impl RpcMethod<1> for MinerCreateBlock {
         })?;
 
         let bls_aggregate = aggregate_from_bls_signatures(bls_sigs)?;
+        budget.check("message packing")?;
 
         let mut block_header = RawBlockHeader {
             miner_address: block_template.miner,
@@ -209,12 +258,43 @@ impl RpcMethod<1> for MinerCreateBlock {
             parent_base_fee,
         };
 
+        // Peers reject blocks from the future, so don't produce one if the
+        // template is ahead of the node clock.
+        if let Err(e) = block_timestamp_checks(
+            &block_header,
+            chrono::Utc::now().timestamp() as u64,
+            ctx.state_manager.sync_config().allowable_clock_drift(),
+        ) {
+            warn!(
+                "Refusing to create block at epoch {}, the clock may be skewed: {e}",
+                block_header.epoch
+            );
+            return Err(anyhow::Error::from(e).into());
+        }
+
         block_header.signature = sign_block_header(&block_header, &worker, ctx.keystore.clone())
             .await?
             .into();
+        budget.check("signing")?;
+        block_size_checks(
+            &block_header,
+            ctx.state_manager.sync_config().max_block_header_size,
+        )
+        .map_err(anyhow::Error::from)?;
+
+        let header = CachingBlockHeader::from(block_header);
+        info!(
+            block = %header.cid(),
+            base = %parent_tipset.key(),
+            parents = %header.parents,
+            epoch = header.epoch,
+            weight = %header.weight,
+            messages = bls_msg_cids.len() + secpk_msg_cids.len(),
+            "Created block"
+        );
 
         Ok(BlockMessage {
-            header: CachingBlockHeader::from(block_header),
+            header,
             bls_messages: bls_msg_cids,
             secpk_messages: secpk_msg_cids,
         })
@@ -289,3 +369,235 @@ impl RpcMethod<3> for MinerGetBaseInfo {
             .await?)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{Tipset, VRFProof};
+    use crate::chain_sync::{SyncConfig, TipsetValidator};
+    use crate::db::MemoryDB;
+    use crate::key_management::{generate_key, KeyStoreConfig};
+    use crate::shim::econ::TokenAmount;
+    use crate::shim::machine::BuiltinActor;
+    use crate::shim::state_tree::{StateTree, StateTreeVersion};
+    use crate::state_manager::StateManager;
+    use crate::test_utils::{builtin_actor, chain_store_with, rpc_ctx};
+    use crate::utils::cid::CidCborExt as _;
+    use num::BigInt;
+
+    const MINER: u64 = 1001;
+    const WORKER: u64 = 1000;
+
+    /// Builds an RPC context over `db` whose chain is a genesis block followed
+    /// by a tipset of `parent_blocks` blocks at epoch 1, which is the head.
+    /// The parent state holds a miner whose worker key is in the keystore.
+    async fn ctx_with_parent<DB: Blockstore + Send + Sync + 'static>(
+        db: Arc<DB>,
+        parent_blocks: u64,
+        sync_config: SyncConfig,
+    ) -> (Ctx<DB>, Arc<Tipset>) {
+        let worker_key = generate_key(SignatureType::Bls).unwrap();
+
+        let mut power = fil_actor_power_state::v13::State::new(&db).unwrap();
+        power.total_quality_adj_power = BigInt::from(1_u64 << 40);
+        let info = fil_actor_miner_state::v13::MinerInfo::new(
+            WORKER,
+            WORKER,
+            vec![],
+            vec![],
+            vec![],
+            fvm_shared4::sector::RegisteredPoStProof::StackedDRGWindow32GiBV1P1,
+        )
+        .unwrap();
+        let miner = fil_actor_miner_state::v13::State::new(
+            &Default::default(),
+            &db,
+            db.put_cbor_default(&info).unwrap(),
+            0,
+            0,
+        )
+        .unwrap();
+        let account = fil_actor_account_state::v13::State {
+            address: worker_key.address.into(),
+        };
+        let mut tree = StateTree::new(db.clone(), StateTreeVersion::V5).unwrap();
+        for (addr, actor) in [
+            (
+                Address::POWER_ACTOR,
+                builtin_actor(db.as_ref(), BuiltinActor::Power, &power),
+            ),
+            (
+                Address::new_id(MINER),
+                builtin_actor(db.as_ref(), BuiltinActor::Miner, &miner),
+            ),
+            (
+                Address::new_id(WORKER),
+                builtin_actor(db.as_ref(), BuiltinActor::Account, &account),
+            ),
+        ] {
+            tree.set_actor(&addr, actor).unwrap();
+        }
+        let parent_state = tree.flush().unwrap();
+
+        let genesis = CachingBlockHeader::new(RawBlockHeader {
+            miner_address: Address::new_id(0),
+            timestamp: 1,
+            ..Default::default()
+        });
+        let messages = TipsetValidator::compute_msg_root(&db, &[], &[]).unwrap();
+        let parent = Arc::new(
+            Tipset::new((0..parent_blocks).map(|i| {
+                CachingBlockHeader::new(RawBlockHeader {
+                    miner_address: Address::new_id(2000 + i),
+                    ticket: Some(Ticket::new(VRFProof::new(vec![i as u8]))),
+                    election_proof: Some(ElectionProof {
+                        win_count: 1,
+                        vrfproof: VRFProof::new(vec![i as u8]),
+                    }),
+                    parents: TipsetKey::from(nunny::vec![*genesis.cid()]),
+                    weight: BigInt::from(100),
+                    epoch: 1,
+                    state_root: parent_state,
+                    messages,
+                    parent_base_fee: TokenAmount::from_atto(1000),
+                    timestamp: 31,
+                    ..Default::default()
+                })
+            }))
+            .unwrap(),
+        );
+
+        let cs = chain_store_with(db, &[Tipset::from(genesis), parent.as_ref().clone()]).await;
+        let chain_config = cs.chain_config().clone();
+        let state_manager =
+            Arc::new(StateManager::new(cs, chain_config, Arc::new(sync_config)).unwrap());
+
+        let mut keystore = KeyStore::new(KeyStoreConfig::Memory).unwrap();
+        keystore
+            .put(
+                &format!("wallet-{}", worker_key.address),
+                worker_key.key_info,
+            )
+            .unwrap();
+        let ctx = rpc_ctx(state_manager, keystore, None);
+        (ctx, parent)
+    }
+
+    fn block_template(parent: &Tipset) -> BlockTemplate {
+        BlockTemplate {
+            miner: Address::new_id(MINER),
+            parents: parent.key().clone(),
+            ticket: Ticket::new(VRFProof::new(vec![9])),
+            eproof: ElectionProof {
+                win_count: 1,
+                vrfproof: VRFProof::new(vec![9]),
+            },
+            beacon_values: vec![],
+            messages: vec![],
+            epoch: parent.epoch() + 1,
+            timestamp: chrono::Utc::now().timestamp() as u64,
+            winning_post_proof: vec![],
+        }
+    }
+
+    #[tokio::test]
+    async fn create_block_on_multi_block_parent() {
+        let db = Arc::new(MemoryDB::default());
+        let (ctx, parent) = ctx_with_parent(db, 2, Default::default()).await;
+        assert_eq!(parent.len(), 2);
+        let (state, receipts) = (
+            Cid::from_cbor_blake2b256(&"state").unwrap(),
+            Cid::from_cbor_blake2b256(&"receipts").unwrap(),
+        );
+        ctx.state_manager
+            .insert_tipset_state(parent.key().clone(), (state, receipts));
+
+        let block = MinerCreateBlock::handle(ctx.clone(), (block_template(&parent),))
+            .await
+            .unwrap();
+        let header = &block.header;
+        assert_eq!(&header.parents, parent.key());
+        assert_eq!(header.epoch, 2);
+        assert_eq!(header.state_root, state);
+        assert_eq!(header.message_receipts, receipts);
+        // log2(2^40) << 8 for the power, plus an eighth of a fifth of it for
+        // each of the two winning blocks of the parent.
+        assert_eq!(
+            header.weight,
+            BigInt::from(100 + 40 * 256 + 40 * 256 * 2 / 10)
+        );
+        // The base fee drops by an eighth when the parent has no messages.
+        assert_eq!(header.parent_base_fee, TokenAmount::from_atto(875));
+        assert!(header.signature.is_some());
+    }
+
+    #[tokio::test]
+    async fn create_block_ahead_of_the_clock() {
+        // A minute ahead is only within the drift allowed by the first
+        // configuration.
+        for (allowable_clock_drift_secs, created) in [(Some(3600), true), (None, false)] {
+            let sync_config = SyncConfig {
+                allowable_clock_drift_secs,
+                ..Default::default()
+            };
+            let db = Arc::new(MemoryDB::default());
+            let (ctx, parent) = ctx_with_parent(db, 1, sync_config).await;
+            ctx.state_manager
+                .insert_tipset_state(parent.key().clone(), (Cid::default(), Cid::default()));
+            let template = BlockTemplate {
+                timestamp: chrono::Utc::now().timestamp() as u64 + 60,
+                ..block_template(&parent)
+            };
+            let result = MinerCreateBlock::handle(ctx, (template,)).await;
+            assert_eq!(result.is_ok(), created, "{allowable_clock_drift_secs:?}");
+        }
+    }
+
+    /// A store that can be made to take `DELAY` for every read.
+    #[derive(Default)]
+    struct SlowDb {
+        db: MemoryDB,
+        slow: std::sync::atomic::AtomicBool,
+    }
+
+    impl SlowDb {
+        const DELAY: Duration = Duration::from_millis(100);
+    }
+
+    impl Blockstore for SlowDb {
+        fn get(&self, k: &Cid) -> anyhow::Result<Option<Vec<u8>>> {
+            if self.slow.load(std::sync::atomic::Ordering::Relaxed) {
+                std::thread::sleep(Self::DELAY);
+            }
+            self.db.get(k)
+        }
+
+        fn put_keyed(&self, k: &Cid, block: &[u8]) -> anyhow::Result<()> {
+            self.db.put_keyed(k, block)
+        }
+    }
+
+    #[tokio::test]
+    async fn create_block_aborts_over_budget() {
+        let db = Arc::new(SlowDb::default());
+        let sync_config = SyncConfig {
+            max_block_build_time_ms: Some(SlowDb::DELAY.as_millis() as u64),
+            ..Default::default()
+        };
+        let (ctx, parent) = ctx_with_parent(db.clone(), 1, sync_config).await;
+        ctx.state_manager
+            .insert_tipset_state(parent.key().clone(), (Cid::default(), Cid::default()));
+
+        // Within budget.
+        MinerCreateBlock::handle(ctx.clone(), (block_template(&parent),))
+            .await
+            .unwrap();
+
+        // Reading the state takes several reads, each as long as the budget.
+        db.slow.store(true, std::sync::atomic::Ordering::Relaxed);
+        let err = MinerCreateBlock::handle(ctx.clone(), (block_template(&parent),))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("during the state read"), "{err}");
+    }
+}
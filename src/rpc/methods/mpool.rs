@@ -34,6 +34,25 @@ impl RpcMethod<1> for MpoolGetNonce {
     }
 }
 
+/// Gets the nonces missing from the pending messages of the specified sender.
+pub enum MpoolNonceGaps {}
+impl RpcMethod<1> for MpoolNonceGaps {
+    const NAME: &'static str = "Forest.MpoolNonceGaps";
+    const PARAM_NAMES: [&'static str; 1] = ["address"];
+    const API_PATHS: ApiPaths = ApiPaths::V0;
+    const PERMISSION: Permission = Permission::Read;
+
+    type Params = (Address,);
+    type Ok = Vec<u64>;
+
+    async fn handle(
+        ctx: Ctx<impl Blockstore + Send + Sync + 'static>,
+        (address,): Self::Params,
+    ) -> Result<Self::Ok, ServerError> {
+        Ok(ctx.mpool.nonce_gaps(&address)?)
+    }
+}
+
 /// Return `Vec` of pending messages in `mpool`
 pub enum MpoolPending {}
 impl RpcMethod<1> for MpoolPending {
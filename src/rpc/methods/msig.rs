@@ -52,26 +52,11 @@ impl RpcMethod<2> for MsigGetPending {
     type Ok = Vec<Transaction>;
 
     async fn handle(
-        ctx: Ctx<impl Blockstore>,
+        ctx: Ctx<impl Blockstore + Send + Sync + 'static>,
         (address, ApiTipsetKey(tsk)): Self::Params,
     ) -> Result<Self::Ok, ServerError> {
         let ts = ctx.chain_store().load_required_tipset_or_heaviest(&tsk)?;
-        let ms: multisig::State = ctx
-            .state_manager
-            .get_actor_state_from_address(&ts, &address)?;
-        let txns = ms
-            .get_pending_txn(ctx.store())?
-            .iter()
-            .map(|txn| Transaction {
-                id: txn.id,
-                to: txn.to.into(),
-                value: txn.value.clone().into(),
-                method: txn.method,
-                params: txn.params.clone(),
-                approved: txn.approved.iter().map(|item| item.into()).collect(),
-            })
-            .collect();
-        Ok(txns)
+        Ok(ctx.state_manager.msig_pending(&address, &ts)?)
     }
 }
 
@@ -17,7 +17,6 @@ use crate::libp2p::NetworkMessage;
 use crate::lotus_json::lotus_json_with_self;
 use crate::networks::{ChainConfig, NetworkChain};
 use crate::shim::actors::market::MarketStateExt as _;
-use crate::shim::actors::state_load::*;
 use crate::shim::actors::verifreg::VerifiedRegistryStateExt as _;
 use crate::shim::actors::{
     market::BalanceTableExt as _,
@@ -33,7 +32,7 @@ use crate::shim::{
     state_tree::ActorState, version::NetworkVersion,
 };
 use crate::state_manager::circulating_supply::GenesisInfo;
-use crate::state_manager::MarketBalance;
+use crate::state_manager::{MarketBalance, TraceSample};
 use crate::utils::db::{
     car_stream::{CarBlock, CarWriter},
     BlockstoreExt as _,
@@ -46,7 +45,6 @@ use ahash::{HashMap, HashMapExt, HashSet};
 use anyhow::Context as _;
 use anyhow::Result;
 use cid::Cid;
-use fil_actor_interface::market::DealState;
 use fil_actor_interface::verifreg::{Allocation, AllocationID, Claim};
 use fil_actor_interface::{
     market, miner,
@@ -106,12 +104,16 @@ impl RpcMethod<2> for StateReplay {
     type Ok = ApiInvocResult;
 
     /// returns the result of executing the indicated message, assuming it was
-    /// executed in the indicated tipset.
+    /// executed in the indicated tipset. Without a tipset, the message is
+    /// looked up on chain and replayed in the tipset that included it.
     async fn handle(
         ctx: Ctx<impl Blockstore + Send + Sync + 'static>,
         (ApiTipsetKey(tsk), message_cid): Self::Params,
     ) -> Result<Self::Ok, ServerError> {
-        let tipset = ctx.chain_store().load_required_tipset_or_heaviest(&tsk)?;
+        let Some(tsk) = tsk else {
+            return Ok(ctx.state_manager.replay_message(message_cid).await?);
+        };
+        let tipset = ctx.chain_index().load_required_tipset(&tsk)?;
         Ok(ctx.state_manager.replay(tipset, message_cid).await?)
     }
 }
@@ -784,43 +786,7 @@ impl RpcMethod<2> for StateMinerAvailableBalance {
         (address, ApiTipsetKey(tsk)): Self::Params,
     ) -> Result<Self::Ok, ServerError> {
         let ts = ctx.chain_store().load_required_tipset_or_heaviest(&tsk)?;
-        let actor = ctx
-            .state_manager
-            .get_required_actor(&address, *ts.parent_state())?;
-        let state = miner::State::load(ctx.store(), actor.code, actor.state)?;
-        let actor_balance: TokenAmount = actor.balance.clone().into();
-        let (vested, available): (TokenAmount, TokenAmount) = match &state {
-            miner::State::V14(s) => (
-                s.check_vested_funds(ctx.store(), ts.epoch())?.into(),
-                s.get_available_balance(&actor_balance.into())?.into(),
-            ),
-            miner::State::V13(s) => (
-                s.check_vested_funds(ctx.store(), ts.epoch())?.into(),
-                s.get_available_balance(&actor_balance.into())?.into(),
-            ),
-            miner::State::V12(s) => (
-                s.check_vested_funds(ctx.store(), ts.epoch())?.into(),
-                s.get_available_balance(&actor_balance.into())?.into(),
-            ),
-            miner::State::V11(s) => (
-                s.check_vested_funds(ctx.store(), ts.epoch())?.into(),
-                s.get_available_balance(&actor_balance.into())?.into(),
-            ),
-            miner::State::V10(s) => (
-                s.check_vested_funds(ctx.store(), ts.epoch())?.into(),
-                s.get_available_balance(&actor_balance.into())?.into(),
-            ),
-            miner::State::V9(s) => (
-                s.check_vested_funds(ctx.store(), ts.epoch())?.into(),
-                s.get_available_balance(&actor_balance.into())?.into(),
-            ),
-            miner::State::V8(s) => (
-                s.check_vested_funds(ctx.store(), ts.epoch())?.into(),
-                s.get_available_balance(&actor_balance.into())?.into(),
-            ),
-        };
-
-        Ok(vested + available)
+        Ok(ctx.state_manager.miner_available_balance(&address, &ts)?)
     }
 }
 
@@ -1338,10 +1304,8 @@ impl RpcMethod<4> for StateGetRandomnessFromTickets {
         (personalization, rand_epoch, entropy, ApiTipsetKey(tsk)): Self::Params,
     ) -> Result<Self::Ok, ServerError> {
         let tipset = ctx.chain_store().load_required_tipset_or_heaviest(&tsk)?;
-        let chain_rand = ctx.state_manager.chain_rand(tipset);
-        let digest = chain_rand.get_chain_randomness(rand_epoch, false)?;
-        let value = crate::state_manager::chain_rand::draw_randomness_from_digest(
-            &digest,
+        let value = ctx.state_manager.get_chain_randomness(
+            tipset,
             personalization,
             rand_epoch,
             &entropy,
@@ -1390,10 +1354,8 @@ impl RpcMethod<4> for StateGetRandomnessFromBeacon {
         (personalization, rand_epoch, entropy, ApiTipsetKey(tsk)): Self::Params,
     ) -> Result<Self::Ok, ServerError> {
         let tipset = ctx.chain_store().load_required_tipset_or_heaviest(&tsk)?;
-        let chain_rand = ctx.state_manager.chain_rand(tipset);
-        let digest = chain_rand.get_beacon_randomness_v3(rand_epoch)?;
-        let value = crate::state_manager::chain_rand::draw_randomness_from_digest(
-            &digest,
+        let value = ctx.state_manager.get_beacon_randomness(
+            tipset,
             personalization,
             rand_epoch,
             &entropy,
@@ -1526,6 +1488,48 @@ impl RpcMethod<1> for StateVMCirculatingSupplyInternal {
     }
 }
 
+/// Returns the total amount of FIL burned by base fees up to the given
+/// tipset, i.e. the base fee times the gas used by every message executed on
+/// the chain.
+pub enum TotalBurned {}
+
+impl RpcMethod<1> for TotalBurned {
+    const NAME: &'static str = "Filecoin.TotalBurned";
+    const PARAM_NAMES: [&'static str; 1] = ["tipset_key"];
+    const API_PATHS: ApiPaths = ApiPaths::V0;
+    const PERMISSION: Permission = Permission::Read;
+
+    type Params = (ApiTipsetKey,);
+    type Ok = TokenAmount;
+
+    async fn handle(
+        ctx: Ctx<impl Blockstore + Send + Sync + 'static>,
+        (ApiTipsetKey(tsk),): Self::Params,
+    ) -> Result<Self::Ok, ServerError> {
+        let ts = ctx.chain_store().load_required_tipset_or_heaviest(&tsk)?;
+        Ok(ctx.state_manager.total_burned(&ts)?)
+    }
+}
+
+pub enum StateTraceSamples {}
+
+impl RpcMethod<0> for StateTraceSamples {
+    const NAME: &'static str = "Forest.StateTraceSamples";
+    const PARAM_NAMES: [&'static str; 0] = [];
+    const API_PATHS: ApiPaths = ApiPaths::V0;
+    const PERMISSION: Permission = Permission::Read;
+
+    type Params = ();
+    type Ok = Vec<TraceSample>;
+
+    async fn handle(
+        ctx: Ctx<impl Blockstore + Send + Sync + 'static>,
+        (): Self::Params,
+    ) -> Result<Self::Ok, ServerError> {
+        Ok(ctx.state_manager.trace_samples())
+    }
+}
+
 pub enum StateListMiners {}
 
 impl RpcMethod<1> for StateListMiners {
@@ -1593,16 +1597,10 @@ impl RpcMethod<2> for StateMarketStorageDeal {
         ctx: Ctx<impl Blockstore + Send + Sync + 'static>,
         (deal_id, ApiTipsetKey(tsk)): Self::Params,
     ) -> Result<Self::Ok, ServerError> {
-        let store = ctx.store();
         let ts = ctx.chain_store().load_required_tipset_or_heaviest(&tsk)?;
-        let market_state: market::State = ctx.state_manager.get_actor_state(&ts)?;
-        let proposals = market_state.proposals(store)?;
-        let proposal = proposals.get(deal_id)?.ok_or_else(|| anyhow::anyhow!("deal {deal_id} not found - deal may not have completed sealing before deal proposal start epoch, or deal may have been slashed"))?;
+        let deal = ctx.state_manager.market_deal(deal_id, &ts)?.ok_or_else(|| anyhow::anyhow!("deal {deal_id} not found - deal may not have completed sealing before deal proposal start epoch, or deal may have been slashed"))?;
 
-        let states = market_state.states(store)?;
-        let state = states.get(deal_id)?.unwrap_or_else(DealState::empty);
-
-        Ok(MarketDeal { proposal, state }.into())
+        Ok(deal.into())
     }
 }
 
@@ -1667,13 +1665,9 @@ impl RpcMethod<3> for StateDealProviderCollateralBounds {
         let power_state: power::State = ctx.state_manager.get_actor_state(&ts)?;
         let reward_state: reward::State = ctx.state_manager.get_actor_state(&ts)?;
 
-        let genesis_info = GenesisInfo::from_chain_config(ctx.chain_config().clone());
-
-        let supply = genesis_info.get_vm_circulating_supply(
-            ts.epoch(),
-            &ctx.store_owned(),
-            ts.parent_state(),
-        )?;
+        let supply = ctx
+            .state_manager
+            .get_vm_circulating_supply(ts.epoch(), ts.parent_state())?;
 
         let power_claim = power_state.total_power();
 
@@ -1730,11 +1724,7 @@ impl RpcMethod<1> for StateGetBeaconEntry {
             };
         }
 
-        let (_, beacon) = ctx.beacon().beacon_for_epoch(epoch)?;
-        let network_version = ctx.state_manager.get_network_version(epoch);
-        let round = beacon.max_beacon_round_for_epoch(network_version, epoch);
-        let entry = beacon.entry(round).await?;
-        Ok(entry)
+        Ok(ctx.state_manager.get_beacon_entry_for_epoch(epoch).await?)
     }
 }
 
@@ -2008,9 +1998,7 @@ impl RpcMethod<3> for StateSectorGetInfo {
         let ts = ctx.chain_store().load_required_tipset_or_heaviest(&tsk)?;
         Ok(ctx
             .state_manager
-            .get_all_sectors(&miner_address, &ts)?
-            .into_iter()
-            .find(|info| info.sector_number == sector_number))
+            .sector_info(&miner_address, sector_number, &ts)?)
     }
 }
 
@@ -2288,7 +2276,7 @@ impl RpcMethod<2> for StateGetAllocations {
         (address, ApiTipsetKey(tsk)): Self::Params,
     ) -> Result<Self::Ok, ServerError> {
         let ts = ctx.chain_store().load_required_tipset_or_heaviest(&tsk)?;
-        Ok(Self::get_allocations(&ctx.store_owned(), &address, &ts)?)
+        Ok(ctx.state_manager.allocations(&address, &ts)?)
     }
 }
 
@@ -225,6 +225,7 @@ mod tests {
             start_time,
             shutdown: mpsc::channel(1).0, // dummy for tests
             tipset_send,
+            gc_requests: None,
         });
         (state, network_rx)
     }
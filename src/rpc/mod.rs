@@ -58,6 +58,8 @@ macro_rules! for_each_method {
         $callback!(crate::rpc::chain::ChainSetHead);
         $callback!(crate::rpc::chain::ChainGetMinBaseFee);
         $callback!(crate::rpc::chain::ChainTipSetWeight);
+        $callback!(crate::rpc::chain::ChainCheckInvariants);
+        $callback!(crate::rpc::chain::ChainGC);
 
         // common vertical
         $callback!(crate::rpc::common::Session);
@@ -99,6 +101,7 @@ macro_rules! for_each_method {
 
         // mpool vertical
         $callback!(crate::rpc::mpool::MpoolGetNonce);
+        $callback!(crate::rpc::mpool::MpoolNonceGaps);
         $callback!(crate::rpc::mpool::MpoolPending);
         $callback!(crate::rpc::mpool::MpoolSelect);
         $callback!(crate::rpc::mpool::MpoolPush);
@@ -163,6 +166,8 @@ macro_rules! for_each_method {
         $callback!(crate::rpc::state::StateCirculatingSupply);
         $callback!(crate::rpc::state::StateVerifiedClientStatus);
         $callback!(crate::rpc::state::StateVMCirculatingSupplyInternal);
+        $callback!(crate::rpc::state::TotalBurned);
+        $callback!(crate::rpc::state::StateTraceSamples);
         $callback!(crate::rpc::state::StateListMiners);
         $callback!(crate::rpc::state::StateListActors);
         $callback!(crate::rpc::state::StateNetworkVersion);
@@ -343,6 +348,8 @@ pub struct RPCState<DB> {
     pub tipset_send: flume::Sender<Arc<Tipset>>,
     pub start_time: chrono::DateTime<chrono::Utc>,
     pub shutdown: mpsc::Sender<()>,
+    /// Requests immediate garbage collection runs, unset when GC is disabled.
+    pub gc_requests: Option<flume::Sender<crate::db::GcRequest>>,
 }
 
 impl<DB: Blockstore> RPCState<DB> {
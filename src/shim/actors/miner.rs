@@ -10,6 +10,8 @@ use fil_actors_shared::fvm_ipld_bitfield::BitField;
 use fvm_ipld_blockstore::Blockstore;
 
 use crate::rpc::types::{SectorOnChainInfo, SectorPreCommitOnChainInfo};
+use crate::shim::{clock::ChainEpoch, econ::TokenAmount};
+use crate::state_manager::VestingEntry;
 use crate::utils::db::CborStoreExt as _;
 
 pub trait MinerStateExt {
@@ -21,6 +23,13 @@ pub trait MinerStateExt {
         sectors: Option<&BitField>,
     ) -> anyhow::Result<Vec<SectorOnChainInfo>>;
 
+    /// Loads the sector with the given number, if it exists.
+    fn load_sector_ext<BS: Blockstore>(
+        &self,
+        store: &BS,
+        sector_number: u64,
+    ) -> anyhow::Result<Option<SectorOnChainInfo>>;
+
     /// Loads the allocated sector numbers
     fn load_allocated_sector_numbers<BS: Blockstore>(&self, store: &BS)
         -> anyhow::Result<BitField>;
@@ -31,6 +40,29 @@ pub trait MinerStateExt {
         store: &BS,
         sector_number: u64,
     ) -> anyhow::Result<Option<SectorPreCommitOnChainInfo>>;
+
+    /// Returns the vesting funds that have unlocked by `current_epoch`,
+    /// without removing them from the state.
+    fn check_vested_funds<BS: Blockstore>(
+        &self,
+        store: &BS,
+        current_epoch: ChainEpoch,
+    ) -> anyhow::Result<TokenAmount>;
+
+    /// Loads the block rewards that are still vesting, by unlock epoch.
+    fn load_vesting_funds_ext<BS: Blockstore>(
+        &self,
+        store: &BS,
+    ) -> anyhow::Result<Vec<VestingEntry>>;
+
+    /// Total pledge collateral of the miner's sectors.
+    fn initial_pledge(&self) -> TokenAmount;
+
+    /// Total collateral of the miner's pre-committed sectors.
+    fn pre_commit_deposits(&self) -> TokenAmount;
+
+    /// Total block rewards of the miner that are still vesting.
+    fn locked_funds(&self) -> TokenAmount;
 }
 
 pub trait PartitionExt {
@@ -134,6 +134,28 @@ impl MinerStateExt for State {
         }
     }
 
+    fn load_sector_ext<BS: Blockstore>(
+        &self,
+        store: &BS,
+        sector_number: u64,
+    ) -> anyhow::Result<Option<SectorOnChainInfo>> {
+        Ok(match self {
+            Self::V8(s) => s.get_sector(store, sector_number)?.map(From::from),
+            Self::V9(s) => s.get_sector(store, sector_number)?.map(From::from),
+            Self::V10(s) => s.get_sector(store, sector_number)?.map(From::from),
+            Self::V11(s) => s.get_sector(store, sector_number)?.map(From::from),
+            Self::V12(s) => s.get_sector(store, sector_number)?.map(From::from),
+            Self::V13(s) => s
+                .get_sector(store, sector_number)
+                .context("failed to load sector")?
+                .map(From::from),
+            Self::V14(s) => s
+                .get_sector(store, sector_number)
+                .context("failed to load sector")?
+                .map(From::from),
+        })
+    }
+
     fn load_allocated_sector_numbers<BS: Blockstore>(
         &self,
         store: &BS,
@@ -180,4 +202,83 @@ impl MinerStateExt for State {
                 .map(SectorPreCommitOnChainInfo::from),
         })
     }
+
+    fn check_vested_funds<BS: Blockstore>(
+        &self,
+        store: &BS,
+        current_epoch: ChainEpoch,
+    ) -> anyhow::Result<TokenAmount> {
+        Ok(match self {
+            Self::V8(s) => s.check_vested_funds(store, current_epoch)?.into(),
+            Self::V9(s) => s.check_vested_funds(store, current_epoch)?.into(),
+            Self::V10(s) => s.check_vested_funds(store, current_epoch)?.into(),
+            Self::V11(s) => s.check_vested_funds(store, current_epoch)?.into(),
+            Self::V12(s) => s.check_vested_funds(store, current_epoch)?.into(),
+            Self::V13(s) => s.check_vested_funds(store, current_epoch)?.into(),
+            Self::V14(s) => s.check_vested_funds(store, current_epoch)?.into(),
+        })
+    }
+
+    fn load_vesting_funds_ext<BS: Blockstore>(
+        &self,
+        store: &BS,
+    ) -> anyhow::Result<Vec<VestingEntry>> {
+        macro_rules! vesting_entries {
+            ($funds:expr) => {
+                $funds
+                    .funds
+                    .into_iter()
+                    .map(|it| VestingEntry {
+                        epoch: it.epoch,
+                        amount: it.amount.into(),
+                    })
+                    .collect()
+            };
+        }
+        Ok(match self {
+            Self::V8(s) => vesting_entries!(s.load_vesting_funds(store)?),
+            Self::V9(s) => vesting_entries!(s.load_vesting_funds(store)?),
+            Self::V10(s) => vesting_entries!(s.load_vesting_funds(store)?),
+            Self::V11(s) => vesting_entries!(s.load_vesting_funds(store)?),
+            Self::V12(s) => vesting_entries!(s.load_vesting_funds(store)?),
+            Self::V13(s) => vesting_entries!(s.load_vesting_funds(store)?),
+            Self::V14(s) => vesting_entries!(s.load_vesting_funds(store)?),
+        })
+    }
+
+    fn initial_pledge(&self) -> TokenAmount {
+        match self {
+            Self::V8(s) => (&s.initial_pledge).into(),
+            Self::V9(s) => (&s.initial_pledge).into(),
+            Self::V10(s) => (&s.initial_pledge).into(),
+            Self::V11(s) => (&s.initial_pledge).into(),
+            Self::V12(s) => (&s.initial_pledge).into(),
+            Self::V13(s) => (&s.initial_pledge).into(),
+            Self::V14(s) => (&s.initial_pledge).into(),
+        }
+    }
+
+    fn pre_commit_deposits(&self) -> TokenAmount {
+        match self {
+            Self::V8(s) => (&s.pre_commit_deposits).into(),
+            Self::V9(s) => (&s.pre_commit_deposits).into(),
+            Self::V10(s) => (&s.pre_commit_deposits).into(),
+            Self::V11(s) => (&s.pre_commit_deposits).into(),
+            Self::V12(s) => (&s.pre_commit_deposits).into(),
+            Self::V13(s) => (&s.pre_commit_deposits).into(),
+            Self::V14(s) => (&s.pre_commit_deposits).into(),
+        }
+    }
+
+    fn locked_funds(&self) -> TokenAmount {
+        match self {
+            Self::V8(s) => (&s.locked_funds).into(),
+            Self::V9(s) => (&s.locked_funds).into(),
+            Self::V10(s) => (&s.locked_funds).into(),
+            Self::V11(s) => (&s.locked_funds).into(),
+            Self::V12(s) => (&s.locked_funds).into(),
+            Self::V13(s) => (&s.locked_funds).into(),
+            Self::V14(s) => (&s.locked_funds).into(),
+        }
+    }
 }
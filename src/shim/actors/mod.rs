@@ -5,6 +5,7 @@ pub mod common;
 pub mod market;
 pub mod miner;
 pub mod multisig;
+pub mod power;
 pub mod verifreg;
 
 pub use common::*;
@@ -0,0 +1,13 @@
+// Copyright 2019-2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+mod state;
+
+use crate::shim::address::Address;
+use fil_actor_interface::power::{Claim, State};
+use fvm_ipld_blockstore::Blockstore;
+
+pub trait PowerStateExt {
+    /// Returns the power claim of every miner registered with the power actor.
+    fn list_all_claims<BS: Blockstore>(&self, store: &BS) -> anyhow::Result<Vec<(Address, Claim)>>;
+}
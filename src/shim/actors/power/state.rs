@@ -0,0 +1,46 @@
+// Copyright 2019-2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::*;
+
+macro_rules! list_claims_pre_v12 {
+    ($state:ident, $store:ident, $version:ident) => {{
+        let claims =
+            fil_actors_shared::$version::make_map_with_root::<_, Claim>(&$state.claims, $store)?;
+        let mut entries = vec![];
+        claims.for_each(|key, claim| {
+            entries.push((key.to_vec(), claim.clone()));
+            Ok(())
+        })?;
+        entries
+            .into_iter()
+            .map(|(key, claim)| Ok((Address::from_bytes(&key)?, claim)))
+            .collect()
+    }};
+}
+
+macro_rules! list_claims {
+    ($state:ident, $store:ident) => {{
+        let claims = $state.load_claims($store)?;
+        let mut entries = vec![];
+        claims.for_each(|addr, claim| {
+            entries.push((addr.into(), claim.clone().into()));
+            Ok(())
+        })?;
+        Ok(entries)
+    }};
+}
+
+impl PowerStateExt for State {
+    fn list_all_claims<BS: Blockstore>(&self, store: &BS) -> anyhow::Result<Vec<(Address, Claim)>> {
+        match self {
+            State::V8(st) => list_claims_pre_v12!(st, store, v8),
+            State::V9(st) => list_claims_pre_v12!(st, store, v9),
+            State::V10(st) => list_claims_pre_v12!(st, store, v10),
+            State::V11(st) => list_claims_pre_v12!(st, store, v11),
+            State::V12(st) => list_claims!(st, store),
+            State::V13(st) => list_claims!(st, store),
+            State::V14(st) => list_claims!(st, store),
+        }
+    }
+}
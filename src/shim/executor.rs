@@ -4,14 +4,16 @@
 use super::trace::ExecutionEvent;
 use crate::shim::{econ::TokenAmount, fvm_shared_latest::error::ExitCode};
 use cid::Cid;
-use fil_actors_shared::fvm_ipld_amt::Amtv0;
+use fil_actors_shared::fvm_ipld_amt::{Amt, Amtv0};
 use fvm2::executor::ApplyRet as ApplyRet_v2;
 use fvm3::executor::ApplyRet as ApplyRet_v3;
 use fvm4::executor::ApplyRet as ApplyRet_v4;
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::RawBytes;
 use fvm_shared2::receipt::Receipt as Receipt_v2;
+use fvm_shared3::event::StampedEvent as StampedEvent_v3;
 pub use fvm_shared3::receipt::Receipt as Receipt_v3;
+use fvm_shared4::event::StampedEvent as StampedEvent_v4;
 use fvm_shared4::receipt::Receipt as Receipt_v4;
 use serde::Serialize;
 
@@ -105,6 +107,14 @@ impl ApplyRet {
             ApplyRet::V4(v4) => v4.exec_trace.iter().cloned().map(Into::into).collect(),
         }
     }
+
+    pub fn events(&self) -> Vec<StampedEvent> {
+        match self {
+            ApplyRet::V2(_) => Vec::new(),
+            ApplyRet::V3(v3) => v3.events.iter().cloned().map(StampedEvent::V3).collect(),
+            ApplyRet::V4(v4) => v4.events.iter().cloned().map(StampedEvent::V4).collect(),
+        }
+    }
 }
 
 // Note: it's impossible to properly derive Deserialize.
@@ -189,6 +199,55 @@ impl Receipt {
     }
 }
 
+/// An event emitted by an actor while executing a message.
+#[derive(PartialEq, Clone, Debug, Serialize)]
+#[serde(untagged)]
+pub enum StampedEvent {
+    V3(StampedEvent_v3),
+    V4(StampedEvent_v4),
+}
+
+impl StampedEvent {
+    /// Bit width of the AMT the FVM builds to compute [`Receipt::events_root`].
+    const AMT_BIT_WIDTH: u32 = 5;
+
+    /// ID of the actor that emitted the event.
+    pub fn emitter(&self) -> u64 {
+        match self {
+            StampedEvent::V3(v3) => v3.emitter,
+            StampedEvent::V4(v4) => v4.emitter,
+        }
+    }
+
+    /// Stores `events` with the same layout as the FVM, so that the returned
+    /// root matches the [`Receipt::events_root`] of the message that emitted
+    /// them. Returns `None` when there are no events.
+    pub fn store_events(
+        db: &impl Blockstore,
+        events: &[StampedEvent],
+    ) -> anyhow::Result<Option<Cid>> {
+        if events.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Amt::new_from_iter_with_bit_width(
+            db,
+            Self::AMT_BIT_WIDTH,
+            events.iter(),
+        )?))
+    }
+
+    pub fn get_events(db: &impl Blockstore, events_root: &Cid) -> anyhow::Result<Vec<Self>> {
+        let mut events = Vec::new();
+        // StampedEvent_v4 and StampedEvent_v3 are identical, use v4 here.
+        let amt = Amt::<StampedEvent_v4, _>::load(events_root, db)?;
+        amt.for_each(|_, event| {
+            events.push(StampedEvent::V4(event.clone()));
+            Ok(())
+        })?;
+        Ok(events)
+    }
+}
+
 impl From<Receipt_v3> for Receipt {
     fn from(other: Receipt_v3) -> Self {
         Receipt::V3(other)
@@ -0,0 +1,148 @@
+// Copyright 2019-2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use crate::shim::econ::TokenAmount;
+
+const GAS_OVERUSE_NUM: u128 = 11;
+const GAS_OVERUSE_DENOM: u128 = 10;
+
+/// Breakdown of the fees paid by a message, following the Filecoin gas
+/// accounting rules. Mirrors the `GasOutputs` computed by the FVM and Lotus.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct GasOutputs {
+    pub base_fee_burn: TokenAmount,
+    pub over_estimation_burn: TokenAmount,
+    pub miner_penalty: TokenAmount,
+    pub miner_tip: TokenAmount,
+    pub refund: TokenAmount,
+    /// In whole gas units.
+    pub gas_refund: u64,
+    /// In whole gas units.
+    pub gas_burned: u64,
+}
+
+impl GasOutputs {
+    pub fn compute(
+        gas_used: u64,
+        gas_limit: u64,
+        base_fee: &TokenAmount,
+        fee_cap: &TokenAmount,
+        gas_premium: &TokenAmount,
+    ) -> Self {
+        let mut out = GasOutputs::default();
+
+        let base_fee_to_pay = if base_fee > fee_cap {
+            out.miner_penalty = (base_fee - fee_cap.clone()) * gas_used;
+            fee_cap
+        } else {
+            base_fee
+        };
+        out.base_fee_burn = base_fee_to_pay * gas_used;
+
+        let miner_tip = if &(base_fee_to_pay + gas_premium) > fee_cap {
+            fee_cap - base_fee_to_pay.clone()
+        } else {
+            gas_premium.clone()
+        };
+        out.miner_tip = &miner_tip * gas_limit;
+
+        (out.gas_refund, out.gas_burned) = compute_gas_overestimation_burn(gas_used, gas_limit);
+        if out.gas_burned != 0 {
+            out.over_estimation_burn = base_fee_to_pay * out.gas_burned;
+            out.miner_penalty += (base_fee - base_fee_to_pay.clone()) * out.gas_burned;
+        }
+
+        let required_funds = fee_cap * gas_limit;
+        out.refund =
+            required_funds - &out.base_fee_burn - &out.miner_tip - &out.over_estimation_burn;
+        out
+    }
+}
+
+/// Returns the gas to refund and the gas to burn for a message that used
+/// `gas_used` out of `gas_limit`. Burns `(N-10)%` of the unused gas, clamped to
+/// `[0%, 100%]`, where `N` is the over-estimation percentage.
+fn compute_gas_overestimation_burn(gas_used: u64, gas_limit: u64) -> (u64, u64) {
+    if gas_used == 0 {
+        return (0, gas_limit);
+    }
+
+    let gas_used = gas_used as u128;
+    let gas_limit = gas_limit as u128;
+    let over = gas_limit
+        .saturating_sub((GAS_OVERUSE_NUM * gas_used) / GAS_OVERUSE_DENOM)
+        .min(gas_used);
+    let gas_remaining = gas_limit.saturating_sub(gas_used);
+    let gas_to_burn = (gas_remaining * over) / gas_used;
+    let refund = gas_remaining.saturating_sub(gas_to_burn);
+
+    (refund as u64, gas_to_burn as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Vectors from Lotus `TestGasBurn`.
+    #[test]
+    fn overestimation_burn() {
+        for (used, limit, refund, burn) in [
+            (100, 200, 10, 90),
+            (100, 150, 30, 20),
+            (1_000, 1_300, 240, 60),
+            (500, 700, 140, 60),
+            (200, 200, 0, 0),
+            (20_000, 21_000, 1_000, 0),
+            (0, 2_000, 0, 2_000),
+            (500, 651, 121, 30),
+            (500, 5_000, 0, 4_500),
+            (7_499_000_000, 7_500_000_000, 1_000_000, 0),
+            (7_500_000_000 / 2, 7_500_000_000, 375_000_000, 3_375_000_000),
+            (1, 7_500_000_000, 0, 7_499_999_999),
+        ] {
+            assert_eq!(
+                compute_gas_overestimation_burn(used, limit),
+                (refund, burn),
+                "used {used}, limit {limit}"
+            );
+        }
+    }
+
+    // Vectors from Lotus `TestGasOutputs`, with a base fee of 10 attoFIL.
+    #[test]
+    fn gas_outputs() {
+        let base_fee = TokenAmount::from_atto(10);
+        for (
+            used,
+            limit,
+            fee_cap,
+            premium,
+            base_fee_burn,
+            over_estimation_burn,
+            miner_penalty,
+            miner_tip,
+            refund,
+        ) in [
+            (100, 110, 11, 1, 1_000, 0, 0, 110, 100),
+            (100, 130, 11, 1, 1_000, 60, 0, 130, 240),
+            (100, 110, 10, 1, 1_000, 0, 0, 0, 100),
+            (100, 110, 6, 1, 600, 0, 400, 0, 60),
+        ] {
+            let out = GasOutputs::compute(
+                used,
+                limit,
+                &base_fee,
+                &TokenAmount::from_atto(fee_cap),
+                &TokenAmount::from_atto(premium),
+            );
+            assert_eq!(out.base_fee_burn, TokenAmount::from_atto(base_fee_burn));
+            assert_eq!(
+                out.over_estimation_burn,
+                TokenAmount::from_atto(over_estimation_burn)
+            );
+            assert_eq!(out.miner_penalty, TokenAmount::from_atto(miner_penalty));
+            assert_eq!(out.miner_tip, TokenAmount::from_atto(miner_tip));
+            assert_eq!(out.refund, TokenAmount::from_atto(refund));
+        }
+    }
+}
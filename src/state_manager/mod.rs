@@ -4,48 +4,59 @@
 pub mod chain_rand;
 pub mod circulating_supply;
 mod errors;
+mod gas_outputs;
 mod metrics;
 pub mod utils;
 pub use self::errors::*;
 use self::utils::structured;
 
 use crate::beacon::{BeaconEntry, BeaconSchedule};
-use crate::blocks::{Tipset, TipsetKey};
+use crate::blocks::{CachingBlockHeader, Tipset, TipsetKey};
 use crate::chain::{
     index::{ChainIndex, ResolveNullTipset},
-    ChainStore, HeadChange,
+    ChainStore, HeadChange, BLOCK_GAS_TARGET,
 };
-use crate::chain_sync::SyncConfig;
+use crate::chain_sync::{SyncConfig, TipsetValidator};
+use crate::cid_collections::CidHashSet;
+use crate::db::{MemoryDB, MemoryOverlay};
 use crate::interpreter::{
     resolve_to_key_addr, ApplyResult, BlockMessages, CalledAt, ExecutionContext,
     IMPLICIT_MESSAGE_GAS_LIMIT, VM,
 };
 use crate::interpreter::{MessageCallbackCtx, VMTrace};
+use crate::ipld::{DfsIter, Ipld};
 use crate::lotus_json::{lotus_json_with_self, LotusJson};
 use crate::message::{ChainMessage, Message as MessageTrait};
 use crate::metrics::HistogramTimerExt;
-use crate::networks::ChainConfig;
+use crate::networks::{ChainConfig, NetworkChain, ACTOR_BUNDLES_METADATA};
+use crate::rpc::eth::{lookup_eth_address, types::EthAddress};
 use crate::rpc::state::{ApiInvocResult, InvocResult, MessageGasCost};
-use crate::rpc::types::{MiningBaseInfo, SectorOnChainInfo};
+use crate::rpc::types::{
+    MarketDeal, MessageLookup, MiningBaseInfo, SectorOnChainInfo, Transaction,
+};
+use crate::shim::machine::{BuiltinActor, BuiltinActorManifest};
 use crate::shim::{
     actors::{
-        miner::MinerStateExt as _, state_load::*, verifreg::VerifiedRegistryStateExt as _,
-        LoadActorStateFromBlockstore,
+        miner::MinerStateExt as _, multisig::MultisigExt as _, power::PowerStateExt as _,
+        state_load::*, verifreg::VerifiedRegistryStateExt as _, LoadActorStateFromBlockstore,
     },
-    executor::ApplyRet,
+    executor::{ApplyRet, StampedEvent},
 };
 use crate::shim::{
     address::{Address, Payload, Protocol},
-    clock::ChainEpoch,
-    econ::TokenAmount,
+    clock::{ChainEpoch, EPOCHS_IN_DAY},
+    deal::DealID,
+    econ::{TokenAmount, BLOCK_GAS_LIMIT},
     executor::Receipt,
     message::Message,
     randomness::Randomness,
-    state_tree::{ActorState, StateTree},
+    sector::{SectorNumber, StoragePower},
+    state_tree::{ActorID, ActorState, StateTree},
     version::NetworkVersion,
 };
-use crate::state_manager::chain_rand::draw_randomness;
+use crate::state_manager::chain_rand::{draw_randomness, draw_randomness_from_digest};
 use crate::state_migration::run_state_migrations;
+use crate::utils::{db::CborStoreExt as _, encoding::from_slice_with_fallback};
 use ahash::{HashMap, HashMapExt};
 use anyhow::{bail, Context as _};
 use bls_signatures::{PublicKey as BlsPublicKey, Serialize as _};
@@ -56,6 +67,7 @@ use fil_actor_interface::init::{self, State};
 use fil_actor_interface::miner::{MinerInfo, MinerPower, Partition};
 use fil_actor_interface::verifreg::{Allocation, AllocationID, Claim};
 use fil_actor_interface::*;
+use fil_actor_miner_state::v12::{BeneficiaryTerm, PendingBeneficiaryChange};
 use fil_actor_verifreg_state::v12::DataCap;
 use fil_actor_verifreg_state::v13::ClaimID;
 use fil_actors_shared::fvm_ipld_amt::Amtv0 as Amt;
@@ -65,22 +77,125 @@ use fil_actors_shared::v13::runtime::Policy;
 use futures::{channel::oneshot, select, FutureExt};
 use fvm_ipld_blockstore::Blockstore;
 use fvm_ipld_encoding::to_vec;
+pub use gas_outputs::GasOutputs;
 use itertools::Itertools as _;
 use lru::LruCache;
 use nonzero_ext::nonzero;
 use num::BigInt;
 use num_traits::identities::Zero;
+use once_cell::sync::OnceCell;
 use parking_lot::Mutex as SyncMutex;
 use rayon::prelude::ParallelBridge;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use std::ops::RangeInclusive;
-use std::{num::NonZeroUsize, sync::Arc};
+use std::{
+    collections::{BTreeMap, VecDeque},
+    num::NonZeroUsize,
+    sync::Arc,
+};
 use tokio::sync::{broadcast::error::RecvError, Mutex as TokioMutex, RwLock};
 use tracing::{error, info, instrument, trace, warn};
 pub use utils::is_valid_for_sending;
 
+#[cfg(test)]
+mod tests;
+
+/// Stores the events emitted by a message, so that the events root of its
+/// receipt resolves in `db`. The FVM only computes that root. Nothing is
+/// stored if the events don't add up to the root of the receipt.
+fn persist_message_events(db: &impl Blockstore, apply_ret: &ApplyRet) -> anyhow::Result<()> {
+    let Some(events_root) = apply_ret.msg_receipt().events_root() else {
+        return Ok(());
+    };
+    let events = apply_ret.events();
+    let computed = StampedEvent::store_events(&MemoryDB::default(), &events)?;
+    if computed != Some(events_root) {
+        warn!(
+            "events don't match the events root {events_root} of their receipt, not storing them"
+        );
+        return Ok(());
+    }
+    StampedEvent::store_events(db, &events)?;
+    Ok(())
+}
+
+/// Collects the receipt, gas costs and execution trace of an applied
+/// message.
+fn invocation_result(ctx: &MessageCallbackCtx<'_>) -> anyhow::Result<ApiInvocResult> {
+    Ok(ApiInvocResult {
+        msg_cid: ctx.message.cid(),
+        msg: ctx.message.message().clone(),
+        msg_rct: Some(ctx.apply_ret.msg_receipt()),
+        error: ctx.apply_ret.failure_info().unwrap_or_default(),
+        duration: ctx.duration.as_nanos().clamp(0, u64::MAX as u128) as u64,
+        gas_cost: MessageGasCost::new(ctx.message.message(), ctx.apply_ret)?,
+        execution_trace: structured::parse_events(ctx.apply_ret.exec_trace()).unwrap_or_default(),
+    })
+}
+
+/// Writes a copy of the state tree at `state_cid` with `overrides` applied to
+/// `store` and returns its root.
+fn apply_actor_overrides(
+    store: &Arc<impl Blockstore>,
+    state_cid: &Cid,
+    overrides: &HashMap<Address, ActorOverride>,
+) -> Result<Cid, Error> {
+    let mut state = StateTree::new_from_root(Arc::clone(store), state_cid)?;
+    for (addr, actor_override) in overrides {
+        let mut actor = state
+            .get_actor(addr)?
+            .ok_or_else(|| Error::State(format!("Actor {addr} to override not found")))?;
+        if let Some(balance) = &actor_override.balance {
+            actor.balance = balance.clone().into();
+        }
+        if let Some(head) = actor_override.state {
+            actor.state = head;
+        }
+        state.set_actor(addr, actor)?;
+    }
+    Ok(state.flush()?)
+}
+
+/// Builds the thread pool dedicated to tipset state computation, see
+/// [`SyncConfig::state_compute_threads`].
+fn state_compute_pool(num_threads: usize) -> anyhow::Result<rayon::ThreadPool> {
+    Ok(rayon::ThreadPoolBuilder::new()
+        .thread_name(|id| format!("state compute thread: {id}"))
+        .num_threads(num_threads)
+        .build()?)
+}
+
+/// Runs `f` on `pool`, reporting a panic as an error like
+/// [`tokio::task::spawn_blocking`] does.
+async fn spawn_on_pool<T: Send + 'static>(
+    pool: &rayon::ThreadPool,
+    f: impl FnOnce() -> Result<T, Error> + Send + 'static,
+) -> Result<T, Error> {
+    let (tx, rx) = oneshot::channel();
+    pool.spawn(move || {
+        let result =
+            std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+                let why = payload
+                    .downcast_ref::<&str>()
+                    .map(|it| it.to_string())
+                    .or_else(|| payload.downcast_ref::<String>().cloned())
+                    .unwrap_or_default();
+                Err(Error::Other(format!("state compute task panicked: {why}")))
+            });
+        let _ = tx.send(result);
+    });
+    rx.await
+        .map_err(|e| Error::Other(format!("state compute task dropped: {e}")))?
+}
+
 const DEFAULT_TIPSET_CACHE_SIZE: NonZeroUsize = nonzero!(1024usize);
+const DEFAULT_CIRC_SUPPLY_CACHE_SIZE: NonZeroUsize = nonzero!(128usize);
+const DEFAULT_ID_CACHE_SIZE: NonZeroUsize = nonzero!(8192usize);
+const DEFAULT_BEACON_ENTRY_CACHE_SIZE: NonZeroUsize = nonzero!(1024usize);
+const DEFAULT_BURNED_CACHE_SIZE: NonZeroUsize = nonzero!(1024usize);
+const TRACE_SAMPLES_CAPACITY: usize = 32;
+const MIN_GAS_PREMIUM: u64 = 100000;
 
 /// Intermediary for retrieving state objects and updating actor states.
 type CidPair = (Cid, Cid);
@@ -208,6 +323,111 @@ pub struct MarketBalance {
 }
 lotus_json_with_self!(MarketBalance);
 
+/// Execution trace of a tipset sampled while computing its state.
+#[derive(Serialize, Deserialize, Clone, JsonSchema)]
+#[serde(rename_all = "PascalCase")]
+pub struct TraceSample {
+    pub epoch: ChainEpoch,
+    #[schemars(with = "LotusJson<TipsetKey>")]
+    #[serde(with = "crate::lotus_json")]
+    pub key: TipsetKey,
+    pub invocations: Vec<ApiInvocResult>,
+}
+lotus_json_with_self!(TraceSample);
+
+/// Base fee and block space usage of a single tipset.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaseFeeSample {
+    pub epoch: ChainEpoch,
+    pub base_fee: TokenAmount,
+    /// Gas used by the messages of the tipset, as a fraction of the gas limit
+    /// of all its blocks.
+    pub gas_used_ratio: f64,
+}
+
+/// An actor whose state differs between two state trees. `old` or `new` is
+/// [`None`] when the actor only exists on one side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActorDiff {
+    pub address: Address,
+    pub old: Option<ActorState>,
+    pub new: Option<ActorState>,
+}
+
+/// The beneficiary of a miner's rewards and its quota, see
+/// [`StateManager::miner_beneficiary`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct BeneficiaryInfo {
+    pub beneficiary: Address,
+    pub term: BeneficiaryTerm,
+    /// A proposed beneficiary change that hasn't been approved yet.
+    pub pending: Option<PendingBeneficiaryChange>,
+}
+
+/// The key of an account actor, see [`StateManager::account_pubkey`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum AccountKey {
+    Bls(BlsPublicKey),
+    /// The `f1` key address. It only carries a hash of the public key, which
+    /// is recovered from signatures when verifying them.
+    Secp256k1(Address),
+}
+
+/// Size statistics of a state tree, see [`StateManager::state_stats`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StateStats {
+    pub actors: usize,
+    /// Size of the blocks reachable from the actor states. Blocks shared
+    /// between actors are only counted once.
+    pub state_bytes: usize,
+    /// Keyed by builtin actor name, or by code CID for unknown actors.
+    pub by_actor_type: HashMap<String, ActorTypeStats>,
+    /// Depth of the HAMT holding the actors.
+    pub hamt_depth: usize,
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ActorTypeStats {
+    pub actors: usize,
+    /// Size of the blocks reachable from the states of the actors of this
+    /// type. Blocks shared between actors are counted for each of them.
+    pub state_bytes: usize,
+}
+
+/// Funds locked across the network, see [`StateManager::locked_funds`].
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LockedFunds {
+    /// Client and provider funds locked in the storage market.
+    pub market_escrow: TokenAmount,
+    pub initial_pledge: TokenAmount,
+    pub precommit_deposits: TokenAmount,
+    /// Block rewards still vesting in miner actors.
+    pub reward_vesting: TokenAmount,
+}
+
+/// Funds of an actor that unlock at `epoch`, see
+/// [`StateManager::vesting_schedule`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VestingEntry {
+    pub epoch: ChainEpoch,
+    pub amount: TokenAmount,
+}
+
+/// Temporary changes to an actor for a simulated call, see
+/// [`StateManager::call_with_overrides`]. Unset fields keep their on-chain
+/// value.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct ActorOverride {
+    pub balance: Option<TokenAmount>,
+    pub state: Option<Cid>,
+}
+
+impl LockedFunds {
+    pub fn total(&self) -> TokenAmount {
+        &self.market_escrow + &self.initial_pledge + &self.precommit_deposits + &self.reward_vesting
+    }
+}
+
 /// State manager handles all interactions with the internal Filecoin actors
 /// state. This encapsulates the [`ChainStore`] functionality, which only
 /// handles chain data, to allow for interactions with the underlying state of
@@ -224,6 +444,23 @@ pub struct StateManager<DB> {
     chain_config: Arc<ChainConfig>,
     sync_config: Arc<SyncConfig>,
     engine: crate::shim::machine::MultiEngine,
+    /// VM circulating supply indexed by epoch and state root.
+    circ_supply_cache: SyncMutex<LruCache<(ChainEpoch, Cid), TokenAmount>>,
+    /// Address to ID resolutions indexed by the tipset they were resolved at.
+    /// Scoping entries to a tipset keeps them correct across reorgs.
+    id_cache: SyncMutex<LruCache<(TipsetKey, Address), Option<ActorID>>>,
+    /// Beacon entries indexed by the epoch they were drawn for. Filled by
+    /// `StateGetBeaconEntry` lookups and by entries verified during block
+    /// validation. Entries never change, so the cache needs no invalidation.
+    beacon_entry_cache: SyncMutex<LruCache<ChainEpoch, BeaconEntry>>,
+    /// Running totals of base fee burns indexed by the tipset they go up to.
+    burned_cache: SyncMutex<LruCache<TipsetKey, TokenAmount>>,
+    /// Dedicated pool for tipset state computation, if configured.
+    compute_pool: Option<rayon::ThreadPool>,
+    /// Most recent sampled execution traces, oldest first.
+    trace_samples: SyncMutex<VecDeque<TraceSample>>,
+    /// Genesis tipset, loaded on first use.
+    genesis: OnceCell<Arc<Tipset>>,
 }
 
 #[allow(clippy::type_complexity)]
@@ -240,6 +477,11 @@ where
     ) -> Result<Self, anyhow::Error> {
         let genesis = cs.genesis_block_header();
         let beacon = Arc::new(chain_config.get_beacon_schedule(genesis.timestamp));
+        let compute_pool = sync_config
+            .state_compute_threads
+            .filter(|n| *n > 0)
+            .map(state_compute_pool)
+            .transpose()?;
 
         Ok(Self {
             cs,
@@ -248,13 +490,62 @@ where
             chain_config,
             sync_config,
             engine: crate::shim::machine::MultiEngine::default(),
+            circ_supply_cache: SyncMutex::new(LruCache::new(DEFAULT_CIRC_SUPPLY_CACHE_SIZE)),
+            id_cache: SyncMutex::new(LruCache::new(DEFAULT_ID_CACHE_SIZE)),
+            beacon_entry_cache: SyncMutex::new(LruCache::new(DEFAULT_BEACON_ENTRY_CACHE_SIZE)),
+            burned_cache: SyncMutex::new(LruCache::new(DEFAULT_BURNED_CACHE_SIZE)),
+            compute_pool,
+            trace_samples: SyncMutex::new(VecDeque::with_capacity(TRACE_SAMPLES_CAPACITY)),
+            genesis: OnceCell::new(),
         })
     }
 
+    /// Returns the genesis tipset of the chain. It is loaded from the store
+    /// once and cached afterwards.
+    pub fn genesis(&self) -> anyhow::Result<Arc<Tipset>> {
+        self.genesis
+            .get_or_try_init(|| {
+                let key = TipsetKey::from(nunny::vec![*self.cs.genesis_block_header().cid()]);
+                let genesis = Tipset::load(self.blockstore(), &key)?
+                    .with_context(|| format!("genesis tipset {key} is not in the store"))?;
+                Ok(Arc::new(genesis))
+            })
+            .cloned()
+    }
+
     pub fn beacon_schedule(&self) -> &Arc<BeaconSchedule> {
         &self.beacon
     }
 
+    /// Returns the latest beacon entry available at `epoch`, asking the
+    /// beacon only the first time an epoch is looked up.
+    pub async fn get_beacon_entry_for_epoch(
+        &self,
+        epoch: ChainEpoch,
+    ) -> anyhow::Result<BeaconEntry> {
+        if let Some(entry) = self.beacon_entry_cache.lock().get(&epoch) {
+            return Ok(entry.clone());
+        }
+        let (_, beacon) = self.beacon.beacon_for_epoch(epoch)?;
+        let round = beacon.max_beacon_round_for_epoch(self.get_network_version(epoch), epoch);
+        let entry = beacon.entry(round).await?;
+        self.beacon_entry_cache.lock().put(epoch, entry.clone());
+        Ok(entry)
+    }
+
+    /// Caches `entry` as the beacon entry for `epoch` if it is the one
+    /// [`StateManager::get_beacon_entry_for_epoch`] would fetch. Block
+    /// validation calls this with verified header entries, so that later
+    /// lookups don't go to the beacon.
+    pub fn cache_beacon_entry(&self, epoch: ChainEpoch, entry: &BeaconEntry) -> anyhow::Result<()> {
+        let (_, beacon) = self.beacon.beacon_for_epoch(epoch)?;
+        let round = beacon.max_beacon_round_for_epoch(self.get_network_version(epoch), epoch);
+        if entry.round() == round {
+            self.beacon_entry_cache.lock().put(epoch, entry.clone());
+        }
+        Ok(())
+    }
+
     /// Returns network version for the given epoch.
     pub fn get_network_version(&self, epoch: ChainEpoch) -> NetworkVersion {
         self.chain_config.network_version(epoch)
@@ -268,6 +559,93 @@ where
         &self.sync_config
     }
 
+    /// Records the state of `key` as if it had been computed.
+    #[cfg(test)]
+    pub(crate) fn insert_tipset_state(&self, key: TipsetKey, state: CidPair) {
+        self.cache.insert(key, state)
+    }
+
+    /// Returns the most recent execution traces sampled while computing
+    /// tipset states, oldest first.
+    pub fn trace_samples(&self) -> Vec<TraceSample> {
+        self.trace_samples.lock().iter().cloned().collect()
+    }
+
+    /// Returns the circulating supply seen by the VM at the given epoch and
+    /// state root. Results are cached.
+    pub fn get_vm_circulating_supply(
+        &self,
+        epoch: ChainEpoch,
+        state_root: &Cid,
+    ) -> anyhow::Result<TokenAmount> {
+        let key = (epoch, *state_root);
+        if let Some(supply) = self.circ_supply_cache.lock().get(&key) {
+            crate::metrics::LRU_CACHE_HIT
+                .get_or_create(&crate::metrics::values::STATE_MANAGER_CIRC_SUPPLY)
+                .inc();
+            return Ok(supply.clone());
+        }
+        crate::metrics::LRU_CACHE_MISS
+            .get_or_create(&crate::metrics::values::STATE_MANAGER_CIRC_SUPPLY)
+            .inc();
+
+        let supply = GenesisInfo::from_chain_config(self.chain_config().clone())
+            .get_vm_circulating_supply(epoch, &self.blockstore_owned(), state_root)?;
+        self.circ_supply_cache.lock().put(key, supply.clone());
+        Ok(supply)
+    }
+
+    /// Returns the code CIDs of the builtin actors deployed at the given
+    /// network version, keyed by actor name.
+    pub fn actor_code_cids(&self, nv: NetworkVersion) -> anyhow::Result<HashMap<String, Cid>> {
+        let bundle = self
+            .chain_config
+            .height_infos
+            .iter()
+            .filter(|(height, info)| NetworkVersion::from(**height) <= nv && info.bundle.is_some())
+            .max_by_key(|(height, info)| (info.epoch, NetworkVersion::from(**height)))
+            .and_then(|(_, info)| info.bundle)
+            .with_context(|| format!("no actor bundle for network version {nv:?}"))?;
+        let metadata = ACTOR_BUNDLES_METADATA
+            .values()
+            .find(|metadata| metadata.bundle_cid == bundle)
+            .with_context(|| format!("no metadata for actor bundle {bundle}"))?;
+        Ok(metadata
+            .manifest
+            .builtin_actors()
+            .map(|(actor, cid)| (actor.name().to_string(), cid))
+            .collect())
+    }
+
+    /// Checks that the builtin actors referenced by the system actor in the
+    /// parent state of the given [`Tipset`] match the actor bundle expected at
+    /// its network version.
+    pub fn verify_actor_code_cids(&self, ts: &Tipset) -> anyhow::Result<()> {
+        let system_state: system::State = self.get_actor_state(ts)?;
+        let builtin_actors = match &system_state {
+            system::State::V8(s) => s.builtin_actors,
+            system::State::V9(s) => s.builtin_actors,
+            system::State::V10(s) => s.builtin_actors,
+            system::State::V11(s) => s.builtin_actors,
+            system::State::V12(s) => s.builtin_actors,
+            system::State::V13(s) => s.builtin_actors,
+            system::State::V14(s) => s.builtin_actors,
+        };
+        let actual: HashMap<_, _> =
+            BuiltinActorManifest::load_manifest(self.blockstore(), &builtin_actors)?
+                .builtin_actors()
+                .map(|(actor, cid)| (actor.name().to_string(), cid))
+                .collect();
+        let nv = self.get_network_version(ts.epoch());
+        let expected = self.actor_code_cids(nv)?;
+        anyhow::ensure!(
+            actual == expected,
+            "builtin actors in state {} don't match the actor bundle for network version {nv:?}",
+            ts.parent_state()
+        );
+        Ok(())
+    }
+
     /// Gets the state tree
     pub fn get_state_tree(&self, state_cid: &Cid) -> anyhow::Result<StateTree<DB>> {
         StateTree::new_from_root(self.blockstore_owned(), state_cid)
@@ -279,6 +657,77 @@ where
         state.get_actor(addr)
     }
 
+    /// Gets actor from the parent state of the given [`Tipset`], if it exists.
+    pub fn get_actor_at_tipset(
+        &self,
+        addr: &Address,
+        ts: &Tipset,
+    ) -> anyhow::Result<Option<ActorState>> {
+        self.get_actor(addr, *ts.parent_state())
+    }
+
+    /// Checks whether the actor at `addr` in the parent state of the given
+    /// [`Tipset`] is of the given builtin type, as deployed at the tipset's
+    /// network version. Returns `false` if the actor doesn't exist.
+    pub fn actor_is_type(
+        &self,
+        addr: &Address,
+        actor_type: BuiltinActor,
+        ts: &Tipset,
+    ) -> anyhow::Result<bool> {
+        let Some(actor) = self.get_actor_at_tipset(addr, ts)? else {
+            return Ok(false);
+        };
+        let nv = self.get_network_version(ts.epoch());
+        let code_cids = self.actor_code_cids(nv)?;
+        let expected = code_cids
+            .get(actor_type.name())
+            .with_context(|| format!("no {} actor at network version {nv:?}", actor_type.name()))?;
+        Ok(&actor.code == expected)
+    }
+
+    /// Returns the key of the account actor at `addr` in the parent state of
+    /// the given [`Tipset`], or `None` if there is no such actor or it isn't an
+    /// account actor. `addr` may be of any protocol, it is resolved to an ID
+    /// first.
+    pub fn account_pubkey(
+        &self,
+        addr: &Address,
+        ts: &Tipset,
+    ) -> anyhow::Result<Option<AccountKey>> {
+        let state = self.get_state_tree(ts.parent_state())?;
+        let Some(id) = state.lookup_id(addr)?.map(Address::new_id) else {
+            return Ok(None);
+        };
+        let Some(actor) = state.get_actor(&id)? else {
+            return Ok(None);
+        };
+        if !crate::shim::actors::is_account_actor(&actor.code) {
+            return Ok(None);
+        }
+        let key = resolve_to_key_addr(&state, self.blockstore(), &id)?;
+        match key.payload() {
+            Payload::BLS(bytes) => Ok(Some(AccountKey::Bls(BlsPublicKey::from_bytes(bytes)?))),
+            Payload::Secp256k1(_) => Ok(Some(AccountKey::Secp256k1(key))),
+            _ => anyhow::bail!("account {addr} has an unexpected key address {key}"),
+        }
+    }
+
+    /// Reloads the BLS and Secp messages of `header` from the blockstore and
+    /// checks that they hash to the message root in the header. This catches
+    /// messages persisted under a CID that doesn't match their content.
+    pub fn verify_block_message_roots(&self, header: &CachingBlockHeader) -> anyhow::Result<()> {
+        let (bls_msgs, secp_msgs) = crate::chain::block_messages(self.blockstore(), header)?;
+        let msg_root = TipsetValidator::compute_msg_root(self.blockstore(), &bls_msgs, &secp_msgs)?;
+        anyhow::ensure!(
+            msg_root == header.messages,
+            "message root mismatch for block {}: header has {}, messages hash to {msg_root}",
+            header.cid(),
+            header.messages
+        );
+        Ok(())
+    }
+
     /// Gets actor state from implicit actor address
     pub fn get_actor_state<S: LoadActorStateFromBlockstore>(
         &self,
@@ -320,6 +769,28 @@ where
         &self.cs
     }
 
+    /// Loads the tipset identified by `key`, checking that its headers form a
+    /// well-formed tipset: same epoch, parents and state root, distinct
+    /// miners, and listed in ticket order.
+    pub fn load_tipset(&self, key: &TipsetKey) -> anyhow::Result<Tipset> {
+        let headers = key
+            .to_cids()
+            .into_iter()
+            .map(|cid| {
+                CachingBlockHeader::load(self.blockstore(), cid)?
+                    .with_context(|| format!("block header {cid} missing from database"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        let tipset = Tipset::new(headers)
+            .with_context(|| format!("blocks of {key} do not form a valid tipset"))?;
+        anyhow::ensure!(
+            tipset.key() == key,
+            "blocks of {key} are not sorted by ticket, expected {}",
+            tipset.key()
+        );
+        Ok(tipset)
+    }
+
     pub fn chain_rand(&self, tipset: Arc<Tipset>) -> ChainRand<DB> {
         ChainRand::new(
             self.chain_config.clone(),
@@ -329,6 +800,102 @@ where
         )
     }
 
+    /// Draws 32 bytes of randomness from the ticket chain at `round`, as seen
+    /// from `tipset`, for the given domain separation tag and entropy.
+    pub fn get_chain_randomness(
+        &self,
+        tipset: Arc<Tipset>,
+        personalization: i64,
+        round: ChainEpoch,
+        entropy: &[u8],
+    ) -> anyhow::Result<[u8; 32]> {
+        let digest = self.chain_rand(tipset).get_chain_randomness(round, false)?;
+        draw_randomness_from_digest(&digest, personalization, round, entropy)
+    }
+
+    /// Like [`Self::get_chain_randomness`], but draws from the drand beacon
+    /// entry for `round` instead of the ticket chain.
+    pub fn get_beacon_randomness(
+        &self,
+        tipset: Arc<Tipset>,
+        personalization: i64,
+        round: ChainEpoch,
+        entropy: &[u8],
+    ) -> anyhow::Result<[u8; 32]> {
+        let digest = self.chain_rand(tipset).get_beacon_randomness_v3(round)?;
+        draw_randomness_from_digest(&digest, personalization, round, entropy)
+    }
+
+    /// Estimates the gas premium a message needs to be included within
+    /// `max_queue_blocks` epochs, from the messages included in the last
+    /// `n_blocks` tipsets of the heaviest chain. This follows Lotus'
+    /// `medianGasPremium`: sampled messages are ranked by premium and the one
+    /// that fills the gas target of `max_queue_blocks` epochs, plus 5%, sets
+    /// the price, averaged with the next better paying message. Estimates
+    /// below the minimum premium are raised to a floor that depends on
+    /// `max_queue_blocks`.
+    pub fn estimate_gas_premium(
+        &self,
+        n_blocks: u64,
+        max_queue_blocks: u64,
+    ) -> Result<TokenAmount, Error> {
+        let n_blocks = n_blocks.max(1);
+        let max_queue_blocks = max_queue_blocks.max(1);
+
+        let mut prices = Vec::new();
+        let mut blocks = 0;
+        let mut ts = self.cs.heaviest_tipset();
+        for _ in 0..n_blocks {
+            if ts.epoch() == 0 {
+                break;
+            }
+            let pts = self
+                .cs
+                .chain_index
+                .load_required_tipset(ts.parents())
+                .map_err(|e| Error::Other(e.to_string()))?;
+            blocks += pts.block_headers().len() as u64;
+            prices.extend(
+                self.cs
+                    .messages_for_tipset(&pts)
+                    .map_err(|e| Error::Other(e.to_string()))?
+                    .iter()
+                    .map(|msg| (msg.message().gas_premium(), msg.message().gas_limit())),
+            );
+            ts = pts;
+        }
+        prices.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+        let target = BLOCK_GAS_TARGET
+            .saturating_mul(blocks)
+            .saturating_mul(max_queue_blocks)
+            / n_blocks;
+        // Move 5% further down the ranking than the gas target.
+        let mut at = target.saturating_add(target / 20);
+        let (mut prev1, mut prev2) = (TokenAmount::zero(), TokenAmount::zero());
+        for (price, limit) in prices {
+            prev2 = std::mem::replace(&mut prev1, price);
+            if limit > at {
+                break;
+            }
+            at -= limit;
+        }
+        let premium = if prev2.is_zero() {
+            prev1
+        } else {
+            (prev1 + prev2).div_floor(2)
+        };
+
+        if premium < TokenAmount::from_atto(MIN_GAS_PREMIUM) {
+            return Ok(TokenAmount::from_atto(match max_queue_blocks {
+                1 => MIN_GAS_PREMIUM * 2,
+                2 => MIN_GAS_PREMIUM * 3 / 2,
+                _ => MIN_GAS_PREMIUM,
+            }));
+        }
+        Ok(premium)
+    }
+
     /// Returns the internal, protocol-level network name.
     pub fn get_network_name(&self, st: &Cid) -> Result<String, Error> {
         let init_act = self
@@ -339,6 +906,30 @@ where
         Ok(state.into_network_name())
     }
 
+    /// Returns the protocol-level network name recorded in the init actor at
+    /// the state of `tipset`.
+    pub fn network_name(&self, tipset: &Tipset) -> Result<String, Error> {
+        self.get_network_name(tipset.parent_state())
+    }
+
+    /// Checks that `tipset` belongs to the configured chain. Devnet names are
+    /// chosen at genesis, so any name that isn't a builtin network is accepted
+    /// for a devnet configuration, and only for it.
+    pub fn validate_network_name(&self, tipset: &Tipset) -> anyhow::Result<()> {
+        let name = self.network_name(tipset)?;
+        let expected = &self.chain_config.network;
+        let matches = match NetworkChain::from_network_name(&name) {
+            Some(found) => found == *expected,
+            None => expected.is_devnet(),
+        };
+        anyhow::ensure!(
+            matches,
+            "state at epoch {} is from network {name}, but the node is configured for {expected}",
+            tipset.epoch()
+        );
+        Ok(())
+    }
+
     /// Returns true if miner has been slashed or is considered invalid.
     pub fn is_miner_slashed(&self, addr: &Address, state_cid: &Cid) -> anyhow::Result<bool, Error> {
         let actor = self
@@ -405,6 +996,38 @@ where
         let state = miner::State::load(self.blockstore(), actor.code, actor.state)?;
         state.load_sectors_ext(self.blockstore(), None)
     }
+
+    /// Returns the sectors of a miner grouped by the epoch at which they are
+    /// scheduled to expire.
+    pub fn sector_expirations(
+        &self,
+        addr: &Address,
+        ts: &Tipset,
+    ) -> anyhow::Result<BTreeMap<ChainEpoch, Vec<SectorNumber>>> {
+        let state: miner::State = self.get_actor_state_from_address(ts, addr)?;
+        let mut expirations = BTreeMap::<_, Vec<_>>::new();
+        for sector in state.load_sectors_ext(self.blockstore(), None)? {
+            expirations
+                .entry(sector.expiration)
+                .or_default()
+                .push(sector.sector_number);
+        }
+        Ok(expirations)
+    }
+
+    /// Returns the on-chain info of a single miner sector, if it exists.
+    pub fn sector_info(
+        &self,
+        addr: &Address,
+        sector_number: u64,
+        ts: &Tipset,
+    ) -> anyhow::Result<Option<SectorOnChainInfo>> {
+        let actor = self
+            .get_actor(addr, *ts.parent_state())?
+            .ok_or_else(|| Error::State("Miner actor not found".to_string()))?;
+        let state = miner::State::load(self.blockstore(), actor.code, actor.state)?;
+        state.load_sector_ext(self.blockstore(), sector_number)
+    }
 }
 
 impl<DB> StateManager<DB>
@@ -419,25 +1042,270 @@ where
         let key = tipset.key();
         self.cache
             .get_or_else(key, || async move {
-                let ts_state = self
-                    .compute_tipset_state(Arc::clone(tipset), NO_CALLBACK, VMTrace::NotTraced)
-                    .await?;
+                let trace_sample_rate = self.sync_config.trace_sample_rate;
+                let ts_state = if trace_sample_rate > 0.0
+                    && rand::random::<f64>() < trace_sample_rate
+                {
+                    self.compute_tipset_state_sampled(Arc::clone(tipset))
+                        .await?
+                } else {
+                    self.compute_tipset_state(Arc::clone(tipset), NO_CALLBACK, VMTrace::NotTraced)
+                        .await?
+                };
                 trace!("Completed tipset state calculation {:?}", tipset.cids());
                 Ok(ts_state)
             })
             .await
     }
 
+    /// Computes the state of the given tipset with tracing enabled, keeping
+    /// the invocation results in the trace sample ring buffer.
+    async fn compute_tipset_state_sampled(
+        self: &Arc<Self>,
+        tipset: Arc<Tipset>,
+    ) -> Result<CidPair, Error> {
+        let invocations = Arc::new(SyncMutex::new(Vec::new()));
+        let callback = {
+            let invocations = Arc::clone(&invocations);
+            move |ctx: MessageCallbackCtx<'_>| {
+                invocations.lock().push(invocation_result(&ctx)?);
+                Ok(())
+            }
+        };
+        let ts_state = self
+            .compute_tipset_state(Arc::clone(&tipset), Some(callback), VMTrace::Traced)
+            .await?;
+        self.push_trace_sample(&tipset, std::mem::take(&mut invocations.lock()));
+        Ok(ts_state)
+    }
+
+    /// Adds the invocations of `tipset` to the trace sample ring buffer,
+    /// evicting the oldest sample when it is full.
+    fn push_trace_sample(&self, tipset: &Tipset, invocations: Vec<ApiInvocResult>) {
+        let mut samples = self.trace_samples.lock();
+        if samples.len() >= TRACE_SAMPLES_CAPACITY {
+            samples.pop_front();
+        }
+        samples.push_back(TraceSample {
+            epoch: tipset.epoch(),
+            key: tipset.key().clone(),
+            invocations,
+        });
+    }
+
+    /// Returns the base fee and gas usage of the last `n_tipsets` non-genesis
+    /// tipsets up to and including `head`, oldest first. This executes any
+    /// tipset whose state isn't cached yet.
+    pub async fn fee_history(
+        self: &Arc<Self>,
+        n_tipsets: usize,
+        head: Arc<Tipset>,
+    ) -> anyhow::Result<Vec<BaseFeeSample>> {
+        let mut samples = Vec::with_capacity(n_tipsets);
+        for ts in head
+            .chain_arc(self.blockstore())
+            .filter(|ts| ts.epoch() > 0)
+            .take(n_tipsets)
+        {
+            let (_, receipt_root) = self.tipset_state(&ts).await?;
+            let gas_used: u64 = Receipt::get_receipts(self.blockstore(), receipt_root)?
+                .iter()
+                .map(Receipt::gas_used)
+                .sum();
+            let max_gas = BLOCK_GAS_LIMIT * ts.block_headers().len() as u64;
+            samples.push(BaseFeeSample {
+                epoch: ts.epoch(),
+                base_fee: ts.block_headers().first().parent_base_fee.clone(),
+                gas_used_ratio: gas_used as f64 / max_gas as f64,
+            });
+        }
+        samples.reverse();
+        Ok(samples)
+    }
+
+    /// Lists the actors whose state differs between the computed states of
+    /// `a` and `b`. This is mostly useful to find out where two nodes
+    /// diverged after executing sibling tipsets.
+    pub async fn diff_state(
+        self: &Arc<Self>,
+        a: &Arc<Tipset>,
+        b: &Arc<Tipset>,
+    ) -> anyhow::Result<Vec<ActorDiff>> {
+        let (root_a, _) = self.tipset_state(a).await?;
+        let (root_b, _) = self.tipset_state(b).await?;
+        self.diff_state_roots(&root_a, &root_b)
+    }
+
+    /// Lists the actors whose state differs between the state trees rooted at
+    /// `a` and `b`, ordered by address.
+    pub fn diff_state_roots(&self, a: &Cid, b: &Cid) -> anyhow::Result<Vec<ActorDiff>> {
+        let mut old_actors = HashMap::default();
+        StateTree::new_from_root(self.blockstore_owned(), a)?.for_each(|addr, actor| {
+            old_actors.insert(addr, actor.clone());
+            Ok(())
+        })?;
+
+        let mut diffs = Vec::new();
+        StateTree::new_from_root(self.blockstore_owned(), b)?.for_each(|addr, actor| {
+            match old_actors.remove(&addr) {
+                Some(old) if &old == actor => {}
+                old => diffs.push(ActorDiff {
+                    address: addr,
+                    old,
+                    new: Some(actor.clone()),
+                }),
+            }
+            Ok(())
+        })?;
+        diffs.extend(old_actors.into_iter().map(|(address, old)| ActorDiff {
+            address,
+            old: Some(old),
+            new: None,
+        }));
+        diffs.sort_by_key(|diff| diff.address.to_string());
+        Ok(diffs)
+    }
+
+    /// Computes the fee breakdown of `msg` given the gas it used and the base
+    /// fee of the tipset it was included in.
+    pub fn gas_outputs(msg: &Message, gas_used: u64, base_fee: &TokenAmount) -> GasOutputs {
+        GasOutputs::compute(
+            gas_used,
+            msg.gas_limit,
+            base_fee,
+            &msg.gas_fee_cap,
+            &msg.gas_premium,
+        )
+    }
+
+    /// Collects size statistics about the parent state of the given
+    /// [`Tipset`].
+    pub fn state_stats(&self, ts: &Tipset) -> anyhow::Result<StateStats> {
+        let actor_names: HashMap<Cid, &str> = ACTOR_BUNDLES_METADATA
+            .values()
+            .flat_map(|metadata| metadata.manifest.builtin_actors())
+            .map(|(actor, code)| (code, actor.name()))
+            .collect();
+
+        let mut stats = StateStats::default();
+        let mut seen = CidHashSet::default();
+        StateTree::new_from_root(self.blockstore_owned(), ts.parent_state())?.for_each(
+            |_, actor| {
+                let (state_bytes, unseen_bytes) = self.reachable_bytes(actor.state, &mut seen)?;
+                let actor_type = match actor_names.get(&actor.code) {
+                    Some(name) => name.to_string(),
+                    None => actor.code.to_string(),
+                };
+                let type_stats = stats.by_actor_type.entry(actor_type).or_default();
+                type_stats.actors += 1;
+                type_stats.state_bytes += state_bytes;
+                stats.actors += 1;
+                stats.state_bytes += unseen_bytes;
+                Ok(())
+            },
+        )?;
+
+        // Versioned state trees wrap the actors HAMT in a `[version, actors, info]`
+        // root.
+        let hamt_root = match self
+            .blockstore()
+            .get_cbor_required::<Ipld>(ts.parent_state())?
+        {
+            Ipld::List(fields) if fields.len() == 3 => match fields.get(1) {
+                Some(Ipld::Link(actors)) => *actors,
+                _ => bail!("invalid state root {}", ts.parent_state()),
+            },
+            _ => *ts.parent_state(),
+        };
+        stats.hamt_depth = self.hamt_depth(&hamt_root)?;
+        Ok(stats)
+    }
+
+    /// Sums the size of the blocks reachable from `root`, and separately of
+    /// those that are not in `seen`, which they are added to.
+    fn reachable_bytes(&self, root: Cid, seen: &mut CidHashSet) -> anyhow::Result<(usize, usize)> {
+        let (mut size, mut unseen_size) = (0, 0);
+        let mut reached = CidHashSet::default();
+        let mut queue = vec![root];
+        while let Some(cid) = queue.pop() {
+            if !reached.insert(cid) {
+                continue;
+            }
+            let Some(data) = self.blockstore().get(&cid)? else {
+                continue;
+            };
+            size += data.len();
+            if seen.insert(cid) {
+                unseen_size += data.len();
+            }
+            if cid.codec() == fvm_ipld_encoding::DAG_CBOR {
+                let ipld: Ipld = from_slice_with_fallback(&data)?;
+                queue.extend(DfsIter::new(ipld).filter_map(|ipld| match ipld {
+                    Ipld::Link(cid) => Some(cid),
+                    _ => None,
+                }));
+            }
+        }
+        Ok((size, unseen_size))
+    }
+
+    /// Returns the number of levels of the HAMT rooted at `root`. HAMT nodes
+    /// are `[bitfield, pointers]`, where a pointer is either a link to a child
+    /// node or a bucket of entries.
+    fn hamt_depth(&self, root: &Cid) -> anyhow::Result<usize> {
+        let mut depth = 0;
+        let mut level = vec![*root];
+        while !level.is_empty() {
+            depth += 1;
+            let mut next = vec![];
+            for node in level {
+                let Ipld::List(fields) = self.blockstore().get_cbor_required::<Ipld>(&node)? else {
+                    bail!("invalid HAMT node {node}");
+                };
+                let Some(Ipld::List(pointers)) = fields.get(1) else {
+                    bail!("invalid HAMT node {node}");
+                };
+                next.extend(pointers.iter().filter_map(|pointer| match pointer {
+                    Ipld::Link(child) => Some(*child),
+                    _ => None,
+                }));
+            }
+            level = next;
+        }
+        Ok(depth)
+    }
+
     #[instrument(skip(self, rand))]
     fn call_raw(
         self: &Arc<Self>,
         msg: &Message,
         rand: ChainRand<DB>,
         tipset: &Arc<Tipset>,
+        state_cid: &Cid,
     ) -> Result<ApiInvocResult, Error> {
-        let mut msg = msg.clone();
+        let circ_supply = self.get_vm_circulating_supply(tipset.epoch(), state_cid)?;
+        self.call_raw_on(
+            Arc::clone(&self.chain_store().chain_index),
+            msg,
+            rand,
+            tipset,
+            state_cid,
+            circ_supply,
+        )
+    }
 
-        let state_cid = tipset.parent_state();
+    /// Like [`Self::call_raw`], but runs the VM over the store of
+    /// `chain_index`, which is where any state it writes ends up.
+    fn call_raw_on<S: Blockstore + Send + Sync + 'static>(
+        &self,
+        chain_index: Arc<ChainIndex<Arc<S>>>,
+        msg: &Message,
+        rand: ChainRand<DB>,
+        tipset: &Arc<Tipset>,
+        state_cid: &Cid,
+        circ_supply: TokenAmount,
+    ) -> Result<ApiInvocResult, Error> {
+        let mut msg = msg.clone();
 
         let tipset_messages = self
             .chain_store()
@@ -452,7 +1320,6 @@ where
         // TODO(elmattic): https://github.com/ChainSafe/forest/issues/3733
 
         let height = tipset.epoch();
-        let genesis_info = GenesisInfo::from_chain_config(self.chain_config().clone());
         let mut vm = VM::new(
             ExecutionContext {
                 heaviest_tipset: Arc::clone(tipset),
@@ -460,13 +1327,9 @@ where
                 epoch: height,
                 rand: Box::new(rand),
                 base_fee: tipset.block_headers().first().parent_base_fee.clone(),
-                circ_supply: genesis_info.get_vm_circulating_supply(
-                    height,
-                    &self.blockstore_owned(),
-                    state_cid,
-                )?,
+                circ_supply,
                 chain_config: self.chain_config().clone(),
-                chain_index: Arc::clone(&self.chain_store().chain_index),
+                chain_index: Arc::clone(&chain_index),
                 timestamp: tipset.min_timestamp(),
             },
             &self.engine,
@@ -481,7 +1344,7 @@ where
         // This is needed to get the correct nonce from the actor state to match the VM
         let state_cid = vm.flush()?;
 
-        let state = StateTree::new_from_root(self.blockstore_owned(), &state_cid)?;
+        let state = StateTree::new_from_root(Arc::clone(&chain_index.db), &state_cid)?;
 
         let from_actor = state
             .get_actor(&msg.from())?
@@ -517,7 +1380,33 @@ where
     ) -> Result<ApiInvocResult, Error> {
         let ts = tipset.unwrap_or_else(|| self.cs.heaviest_tipset());
         let chain_rand = self.chain_rand(Arc::clone(&ts));
-        self.call_raw(message, chain_rand, &ts)
+        self.call_raw(message, chain_rand, &ts, ts.parent_state())
+    }
+
+    /// Like [`StateManager::call`], but runs the message on top of the tipset
+    /// state with `overrides` applied to the given actors. The overridden
+    /// state, and everything the call writes, lives in memory and is dropped
+    /// once the call returns.
+    pub fn call_with_overrides(
+        self: &Arc<Self>,
+        message: &Message,
+        tipset: Option<Arc<Tipset>>,
+        overrides: &HashMap<Address, ActorOverride>,
+    ) -> Result<ApiInvocResult, Error> {
+        let ts = tipset.unwrap_or_else(|| self.cs.heaviest_tipset());
+        let store = Arc::new(MemoryOverlay::new(self.blockstore_owned()));
+        let state_cid = apply_actor_overrides(&store, ts.parent_state(), overrides)?;
+        let circ_supply = GenesisInfo::from_chain_config(self.chain_config().clone())
+            .get_vm_circulating_supply(ts.epoch(), &store, &state_cid)?;
+        let chain_rand = self.chain_rand(Arc::clone(&ts));
+        self.call_raw_on(
+            Arc::new(ChainIndex::new(store)),
+            message,
+            chain_rand,
+            &ts,
+            &state_cid,
+            circ_supply,
+        )
     }
 
     /// Computes message on the given [Tipset] state, after applying other
@@ -539,7 +1428,6 @@ where
         // Since we're simulating a future message, pretend we're applying it in the
         // "next" tipset
         let epoch = ts.epoch() + 1;
-        let genesis_info = GenesisInfo::from_chain_config(self.chain_config().clone());
         // FVM requires a stack size of 64MiB. The alternative is to use `ThreadedExecutor` from
         // FVM, but that introduces some constraints, and possible deadlocks.
         let (ret, _) = stacker::grow(64 << 20, || -> ApplyResult {
@@ -550,11 +1438,7 @@ where
                     epoch,
                     rand: Box::new(chain_rand),
                     base_fee: ts.block_headers().first().parent_base_fee.clone(),
-                    circ_supply: genesis_info.get_vm_circulating_supply(
-                        epoch,
-                        &self.blockstore_owned(),
-                        &st,
-                    )?,
+                    circ_supply: self.get_vm_circulating_supply(epoch, &st)?,
                     chain_config: self.chain_config().clone(),
                     chain_index: Arc::clone(&self.chain_store().chain_index),
                     timestamp: ts.min_timestamp(),
@@ -591,6 +1475,26 @@ where
             .map_err(|e| Error::Other(format!("{e}")))?
     }
 
+    /// Replays a message that is already on chain, in the tipset that
+    /// included it.
+    pub async fn replay_message(self: &Arc<Self>, mcid: Cid) -> Result<ApiInvocResult, Error> {
+        let ts = self.message_inclusion_tipset(mcid).await?;
+        self.replay(ts, mcid).await
+    }
+
+    /// Returns the tipset that included a message, i.e. the parent of the
+    /// tipset it was executed in.
+    async fn message_inclusion_tipset(self: &Arc<Self>, mcid: Cid) -> Result<Arc<Tipset>, Error> {
+        let (executed, _) = self
+            .search_for_message(None, mcid, None, Some(true))
+            .await?
+            .ok_or_else(|| Error::Other(format!("message {mcid} not found on chain")))?;
+        self.cs
+            .chain_index
+            .load_required_tipset(executed.parents())
+            .map_err(|err| Error::Other(format!("failed to load tipset: {err}")))
+    }
+
     /// Blocking version of `replay`
     pub fn replay_blocking(
         self: &Arc<Self>,
@@ -605,16 +1509,7 @@ where
                 CalledAt::Applied | CalledAt::Reward
                     if api_invoc_result.is_none() && ctx.cid == mcid =>
                 {
-                    api_invoc_result = Some(ApiInvocResult {
-                        msg_cid: ctx.message.cid(),
-                        msg: ctx.message.message().clone(),
-                        msg_rct: Some(ctx.apply_ret.msg_receipt()),
-                        error: ctx.apply_ret.failure_info().unwrap_or_default(),
-                        duration: ctx.duration.as_nanos().clamp(0, u64::MAX as u128) as u64,
-                        gas_cost: MessageGasCost::new(ctx.message.message(), ctx.apply_ret)?,
-                        execution_trace: structured::parse_events(ctx.apply_ret.exec_trace())
-                            .unwrap_or_default(),
-                    });
+                    api_invoc_result = Some(invocation_result(&ctx)?);
                     anyhow::bail!(REPLAY_HALT);
                 }
                 _ => Ok(()), // ignored
@@ -713,10 +1608,17 @@ where
         enable_tracing: VMTrace,
     ) -> Result<CidPair, Error> {
         let this = Arc::clone(self);
-        tokio::task::spawn_blocking(move || {
-            this.compute_tipset_state_blocking(tipset, callback, enable_tracing)
-        })
-        .await?
+        if let Some(pool) = self.compute_pool.as_ref() {
+            spawn_on_pool(pool, move || {
+                this.compute_tipset_state_blocking(tipset, callback, enable_tracing)
+            })
+            .await
+        } else {
+            tokio::task::spawn_blocking(move || {
+                this.compute_tipset_state_blocking(tipset, callback, enable_tracing)
+            })
+            .await?
+        }
     }
 
     /// Blocking version of `compute_tipset_state`
@@ -724,9 +1626,20 @@ where
     pub fn compute_tipset_state_blocking(
         &self,
         tipset: Arc<Tipset>,
-        callback: Option<impl FnMut(MessageCallbackCtx<'_>) -> anyhow::Result<()>>,
+        mut callback: Option<impl FnMut(MessageCallbackCtx<'_>) -> anyhow::Result<()>>,
         enable_tracing: VMTrace,
     ) -> Result<CidPair, Error> {
+        let persist_events = self.sync_config.persist_events;
+        let callback =
+            (persist_events || callback.is_some()).then_some(|ctx: MessageCallbackCtx<'_>| {
+                if persist_events && matches!(ctx.at, CalledAt::Applied) {
+                    persist_message_events(self.blockstore(), ctx.apply_ret)?;
+                }
+                match &mut callback {
+                    Some(callback) => callback(ctx),
+                    None => Ok(()),
+                }
+            });
         Ok(apply_block_messages(
             self.chain_store().genesis_block_header().timestamp,
             Arc::clone(&self.chain_store().chain_index),
@@ -1023,6 +1936,72 @@ where
         }
     }
 
+    /// Searches for an executed message like [`Self::search_for_message`],
+    /// returning its receipt along with the events it emitted. Events are only
+    /// stored when [`SyncConfig::persist_events`] is set.
+    pub async fn receipt_and_events(
+        self: &Arc<Self>,
+        msg_cid: Cid,
+    ) -> Result<(Receipt, Vec<StampedEvent>), Error> {
+        let (_, receipt) = self
+            .search_for_message(None, msg_cid, None, None)
+            .await?
+            .ok_or_else(|| Error::Other(format!("message {msg_cid} not found")))?;
+        let events = match receipt.events_root() {
+            Some(events_root) if !self.blockstore().has(&events_root)? => {
+                return Err(Error::Other(format!(
+                    "events of message {msg_cid} are not stored"
+                )))
+            }
+            Some(events_root) => StampedEvent::get_events(self.blockstore(), &events_root)?,
+            None => Vec::new(),
+        };
+        Ok((receipt, events))
+    }
+
+    /// Searches the tipsets between `from_epoch` and `to_epoch` (inclusive)
+    /// for the one in which a message was executed, walking back from
+    /// `to_epoch` and stopping at the first match. Returns `None` if the
+    /// message was not executed within the range. `to_epoch` is capped at the
+    /// current head.
+    pub fn search_message(
+        &self,
+        msg_cid: Cid,
+        from_epoch: ChainEpoch,
+        to_epoch: ChainEpoch,
+    ) -> Result<Option<MessageLookup>, Error> {
+        let message = crate::chain::get_chain_message(self.blockstore(), &msg_cid)
+            .map_err(|err| Error::Other(format!("failed to load message {err}")))?;
+        let head = self.cs.heaviest_tipset();
+        let to_epoch = to_epoch.min(head.epoch());
+        if from_epoch > to_epoch {
+            return Ok(None);
+        }
+        let end = self
+            .cs
+            .chain_index
+            .tipset_by_height(to_epoch, head, ResolveNullTipset::TakeOlder)
+            .map_err(|err| Error::Other(format!("failed to load tipset: {err}")))?;
+        for tipset in self
+            .cs
+            .chain_index
+            .chain(end)
+            .take_while(|tipset| tipset.epoch() >= from_epoch)
+        {
+            if let Some(receipt) = self.tipset_executed_message(&tipset, &message, true)? {
+                let return_dec = receipt.return_data().deserialize().unwrap_or(Ipld::Null);
+                return Ok(Some(MessageLookup {
+                    receipt,
+                    tipset: tipset.key().clone(),
+                    height: tipset.epoch(),
+                    message: msg_cid,
+                    return_dec,
+                }));
+            }
+        }
+        Ok(None)
+    }
+
     /// Returns a BLS public key from provided address
     pub fn get_bls_public_key(
         db: &Arc<DB>,
@@ -1045,12 +2024,21 @@ where
 
     /// Looks up ID [Address] from the state at the given [Tipset].
     pub fn lookup_id(&self, addr: &Address, ts: &Tipset) -> Result<Option<Address>, Error> {
+        if addr.protocol() == Protocol::ID {
+            return Ok(Some(*addr));
+        }
+        let key = (ts.key().clone(), *addr);
+        if let Some(id) = self.id_cache.lock().get(&key) {
+            return Ok(id.map(Address::new_id));
+        }
+
         let state_tree = StateTree::new_from_root(self.blockstore_owned(), ts.parent_state())
             .map_err(|e| format!("{e:?}"))?;
-        Ok(state_tree
+        let id = state_tree
             .lookup_id(addr)
-            .map_err(|e| Error::Other(e.to_string()))?
-            .map(Address::new_id))
+            .map_err(|e| Error::Other(e.to_string()))?;
+        self.id_cache.lock().put(key, id);
+        Ok(id.map(Address::new_id))
     }
 
     /// Looks up required ID [Address] from the state at the given [Tipset].
@@ -1059,6 +2047,84 @@ where
             .ok_or_else(|| Error::Other(format!("Failed to lookup the id address {addr}")))
     }
 
+    /// Returns the Ethereum address of an actor from the state at the given
+    /// [Tipset]. `f410f` addresses are converted directly. Other addresses
+    /// map to the actor's delegated address if it has one, or to the masked-ID
+    /// form otherwise. Returns `None` if a non-ID address can't be resolved;
+    /// like the state tree lookup, ID addresses map to their masked-ID form
+    /// without checking that the actor exists.
+    pub fn eth_address_for(
+        &self,
+        addr: &Address,
+        ts: &Tipset,
+    ) -> anyhow::Result<Option<EthAddress>> {
+        let state_tree = self.get_state_tree(ts.parent_state())?;
+        lookup_eth_address(addr, &state_tree)
+    }
+
+    /// Returns the ID [Address] of the actor behind an Ethereum address from
+    /// the state at the given [Tipset]. Both masked-ID and `f410f` forms are
+    /// supported. Returns `None` if an `f410f` address isn't assigned to an
+    /// actor. Masked-ID addresses are returned as ID addresses without checking
+    /// that the actor exists.
+    pub fn filecoin_address_for(
+        &self,
+        eth_addr: &EthAddress,
+        ts: &Tipset,
+    ) -> anyhow::Result<Option<Address>> {
+        Ok(self.lookup_id(&eth_addr.to_filecoin_address()?, ts)?)
+    }
+
+    /// Returns the running total of base fee burns up to the given [`Tipset`],
+    /// i.e. [`StateManager::base_fee_burned`] summed over it and its
+    /// ancestors. The total is extended from the closest tipset it is known
+    /// for, so following the head only computes the burns of new tipsets.
+    pub fn total_burned(&self, ts: &Tipset) -> anyhow::Result<TokenAmount> {
+        // Walk back to the closest tipset with a known total, or genesis.
+        let mut unknown = vec![];
+        let mut total = TokenAmount::zero();
+        let mut cur = ts.clone();
+        loop {
+            if let Some(known) = self.burned_cache.lock().get(cur.key()) {
+                total = known.clone();
+                break;
+            }
+            if cur.epoch() == 0 {
+                unknown.push(cur);
+                break;
+            }
+            let parent = Tipset::load_required(self.blockstore(), cur.parents())?;
+            unknown.push(std::mem::replace(&mut cur, parent));
+        }
+        for ts in unknown.into_iter().rev() {
+            total += self.base_fee_burned(&ts)?;
+            self.burned_cache
+                .lock()
+                .put(ts.key().clone(), total.clone());
+        }
+        Ok(total)
+    }
+
+    /// Returns the base fee burned by the messages of the parent of `ts`,
+    /// whose receipts `ts` carries. Each message burns its gas used times the
+    /// base fee, capped at its fee cap like the VM does.
+    pub fn base_fee_burned(&self, ts: &Tipset) -> anyhow::Result<TokenAmount> {
+        if ts.epoch() == 0 {
+            return Ok(TokenAmount::zero());
+        }
+        let parent = Tipset::load_required(self.blockstore(), ts.parents())?;
+        let base_fee = &parent.min_ticket_block().parent_base_fee;
+        let messages = self.chain_store().messages_for_tipset(&parent)?;
+        let receipts =
+            Receipt::get_receipts(self.blockstore(), ts.min_ticket_block().message_receipts)?;
+        Ok(messages
+            .iter()
+            .zip(receipts)
+            .fold(TokenAmount::zero(), |burned, (message, receipt)| {
+                burned + message.gas_fee_cap().min(base_fee.clone()) * receipt.gas_used()
+            }))
+    }
+
     /// Retrieves market state
     pub fn market_state(&self, ts: &Tipset) -> Result<market::State, Error> {
         let actor = self.get_required_actor(&Address::MARKET_ACTOR, *ts.parent_state())?;
@@ -1088,6 +2154,43 @@ where
         Ok(out)
     }
 
+    /// Retrieves the proposal and state of a storage deal, if the deal exists.
+    /// Deals that have not been activated yet come with an empty state.
+    pub fn market_deal(&self, deal_id: DealID, ts: &Tipset) -> Result<Option<MarketDeal>, Error> {
+        let market_state = self.market_state(ts)?;
+        let Some(proposal) = market_state.proposals(self.blockstore())?.get(deal_id)? else {
+            return Ok(None);
+        };
+        let state = market_state
+            .states(self.blockstore())?
+            .get(deal_id)?
+            .unwrap_or_else(market::DealState::empty);
+        Ok(Some(MarketDeal { proposal, state }))
+    }
+
+    /// Retrieves the storage deals that have been published but not activated
+    /// yet, keyed by deal ID.
+    pub fn pending_deals(&self, ts: &Tipset) -> Result<HashMap<DealID, MarketDeal>, Error> {
+        let market_state = self.market_state(ts)?;
+        let states = market_state.states(self.blockstore())?;
+        let mut out = HashMap::new();
+        market_state
+            .proposals(self.blockstore())?
+            .for_each(|deal_id, proposal| {
+                if states.get(deal_id)?.is_none() {
+                    out.insert(
+                        deal_id,
+                        MarketDeal {
+                            proposal: proposal?,
+                            state: market::DealState::empty(),
+                        },
+                    );
+                }
+                Ok(())
+            })?;
+        Ok(out)
+    }
+
     /// Retrieves miner info.
     pub fn miner_info(&self, addr: &Address, ts: &Tipset) -> Result<MinerInfo, Error> {
         let actor = self
@@ -1098,6 +2201,31 @@ where
         Ok(state.info(self.blockstore())?)
     }
 
+    /// Retrieves the current beneficiary of a miner and its quota.
+    pub fn miner_beneficiary(&self, addr: &Address, ts: &Tipset) -> Result<BeneficiaryInfo, Error> {
+        let info = self.miner_info(addr, ts)?;
+        Ok(BeneficiaryInfo {
+            beneficiary: info.beneficiary.into(),
+            term: info.beneficiary_term,
+            pending: info.pending_beneficiary_term,
+        })
+    }
+
+    /// Retrieves the balance a miner can withdraw: its vested funds plus the
+    /// actor balance not held as locked funds, pre-commit deposits, initial
+    /// pledge or fee debt.
+    pub fn miner_available_balance(
+        &self,
+        addr: &Address,
+        ts: &Tipset,
+    ) -> anyhow::Result<TokenAmount> {
+        let actor = self.get_required_actor(addr, *ts.parent_state())?;
+        let state = miner::State::load(self.blockstore(), actor.code, actor.state)?;
+        let vested = state.check_vested_funds(self.blockstore(), ts.epoch())?;
+        let available: TokenAmount = state.available_balance(actor.balance.atto())?.into();
+        Ok(vested + available)
+    }
+
     /// Retrieves miner faults.
     pub fn miner_faults(&self, addr: &Address, ts: &Tipset) -> Result<BitField, Error> {
         self.all_partition_sectors(addr, ts, |partition| partition.faulty_sectors().clone())
@@ -1153,6 +2281,146 @@ where
         })
     }
 
+    /// Returns the power claim of every miner registered with the power actor
+    /// at the given tipset.
+    pub fn all_power_claims(&self, ts: &Tipset) -> Result<Vec<(Address, power::Claim)>, Error> {
+        let actor = self
+            .get_actor(&Address::POWER_ACTOR, *ts.parent_state())?
+            .ok_or_else(|| Error::State("Power actor address could not be resolved".to_string()))?;
+        let state = power::State::load(self.blockstore(), actor.code, actor.state)?;
+        Ok(state.list_all_claims(self.blockstore())?)
+    }
+
+    /// Returns the funds of a miner or multisig actor that are still vesting
+    /// at the given tipset. Multisig funds vest linearly and are reported in
+    /// daily steps until fully vested.
+    pub fn vesting_schedule(
+        &self,
+        addr: &Address,
+        ts: &Tipset,
+    ) -> anyhow::Result<Vec<VestingEntry>> {
+        let actor = self.get_required_actor(addr, *ts.parent_state())?;
+        if crate::shim::actors::is_miner_actor(&actor.code) {
+            let state = miner::State::load(self.blockstore(), actor.code, actor.state)?;
+            state.load_vesting_funds_ext(self.blockstore())
+        } else if crate::shim::actors::is_multisig_actor(&actor.code) {
+            let state = multisig::State::load(self.blockstore(), actor.code, actor.state)?;
+            let vesting = state.get_vesting_schedule()?;
+            let end = vesting.start_epoch + vesting.unlock_duration;
+            let mut entries = vec![];
+            // Nothing unlocks before the vesting start.
+            let mut epoch = ts.epoch().max(vesting.start_epoch);
+            // `locked_balance` takes the epochs elapsed since the vesting start.
+            let locked_at = |epoch| -> anyhow::Result<TokenAmount> {
+                Ok(state.locked_balance(epoch - vesting.start_epoch)?.into())
+            };
+            let mut locked = locked_at(epoch)?;
+            while epoch < end {
+                epoch = (epoch + EPOCHS_IN_DAY).min(end);
+                let next = locked_at(epoch)?;
+                entries.push(VestingEntry {
+                    epoch,
+                    amount: &locked - next.clone(),
+                });
+                locked = next;
+            }
+            Ok(entries)
+        } else {
+            bail!("actor {addr} has no vesting schedule")
+        }
+    }
+
+    /// Returns the funds locked in the storage market and in every miner
+    /// registered with the power actor at the given tipset.
+    pub fn locked_funds(&self, ts: &Tipset) -> anyhow::Result<LockedFunds> {
+        let mut locked = LockedFunds {
+            market_escrow: self.market_state(ts)?.total_locked().into(),
+            ..Default::default()
+        };
+        let power: power::State = self.get_actor_state(ts)?;
+        for miner in power.list_all_miners(self.blockstore())? {
+            let state: miner::State = self.get_actor_state_from_address(ts, &miner.into())?;
+            locked.initial_pledge += state.initial_pledge();
+            locked.precommit_deposits += state.pre_commit_deposits();
+            locked.reward_vesting += state.locked_funds();
+        }
+        Ok(locked)
+    }
+
+    /// Returns the transactions proposed to a multisig actor that have not
+    /// been approved yet.
+    pub fn msig_pending(&self, addr: &Address, ts: &Tipset) -> anyhow::Result<Vec<Transaction>> {
+        let ms: multisig::State = self.get_actor_state_from_address(ts, addr)?;
+        Ok(ms
+            .get_pending_txn(self.blockstore())?
+            .into_iter()
+            .map(|txn| Transaction {
+                id: txn.id,
+                to: txn.to.into(),
+                value: txn.value.into(),
+                method: txn.method,
+                params: txn.params,
+                approved: txn.approved.into_iter().map(Address::from).collect(),
+            })
+            .collect())
+    }
+
+    /// Returns every robust address mapped to an ID by the init actor at the
+    /// given tipset, ordered by actor ID.
+    pub fn init_address_map(&self, ts: &Tipset) -> anyhow::Result<Vec<(Address, ActorID)>> {
+        macro_rules! collect_address_map {
+            ($state:expr, $make_map_with_root:path) => {{
+                let mut entries = Vec::new();
+                $make_map_with_root(&$state.address_map, self.blockstore())?.for_each(
+                    |addr, id| {
+                        entries.push((Address::from_bytes(addr)?, *id));
+                        Ok(())
+                    },
+                )?;
+                entries
+            }};
+        }
+
+        let state: init::State = self.get_actor_state(ts)?;
+        let mut entries = match state {
+            init::State::V0(_) => bail!("init actor v0 is not supported"),
+            init::State::V8(s) => {
+                collect_address_map!(s, fil_actors_shared::v8::make_map_with_root::<_, ActorID>)
+            }
+            init::State::V9(s) => {
+                collect_address_map!(s, fil_actors_shared::v9::make_map_with_root::<_, ActorID>)
+            }
+            init::State::V10(s) => {
+                collect_address_map!(s, fil_actors_shared::v10::make_map_with_root::<_, ActorID>)
+            }
+            init::State::V11(s) => {
+                collect_address_map!(s, fil_actors_shared::v11::make_map_with_root::<_, ActorID>)
+            }
+            init::State::V12(s) => {
+                collect_address_map!(s, fil_actors_shared::v12::make_map_with_root::<_, ActorID>)
+            }
+            init::State::V13(s) => {
+                collect_address_map!(s, fil_actors_shared::v13::make_map_with_root::<_, ActorID>)
+            }
+            init::State::V14(s) => {
+                let mut entries = Vec::new();
+                fil_actor_init_state::v14::AddressMap::load(
+                    self.blockstore(),
+                    &s.address_map,
+                    fil_actors_shared::v14::DEFAULT_HAMT_CONFIG,
+                    "address_map",
+                )?
+                .for_each(|addr, id| {
+                    entries.push((addr.into(), *id));
+                    Ok(())
+                })?;
+                entries
+            }
+        };
+        entries.sort_by_key(|(_, id)| *id);
+        Ok(entries)
+    }
+
     /// Similar to `resolve_to_key_addr` in the `forest_vm` [`crate::state_manager`] but does not
     /// allow `Actor` type of addresses. Uses `ts` to generate the VM state.
     pub async fn resolve_to_key_addr(
@@ -1377,6 +2645,16 @@ where
         state.get_allocation(self.blockstore(), id_address.id()?, allocation_id)
     }
 
+    /// Retrieves the datacap allocations made by the given client.
+    pub fn allocations(
+        &self,
+        client: &Address,
+        ts: &Tipset,
+    ) -> anyhow::Result<HashMap<AllocationID, Allocation>> {
+        let state = self.get_verified_registry_actor_state(ts)?;
+        state.get_allocations(self.blockstore(), client)
+    }
+
     pub fn get_all_allocations(
         &self,
         ts: &Tipset,
@@ -1390,6 +2668,16 @@ where
         addr: &Address,
         ts: &Tipset,
     ) -> anyhow::Result<Option<DataCap>> {
+        self.datacap_balance(addr, ts)
+    }
+
+    /// Returns the remaining datacap of a verified client at the given tipset, read from the
+    /// datacap actor, or from the verified registry before network version 17.
+    pub fn datacap_balance(
+        &self,
+        addr: &Address,
+        ts: &Tipset,
+    ) -> anyhow::Result<Option<StoragePower>> {
         let id = self.lookup_required_id(addr, ts)?;
         let network_version = self.get_network_version(ts.epoch());
 
@@ -1404,7 +2692,7 @@ where
         let act = self
             .get_actor(&Address::DATACAP_TOKEN_ACTOR, *ts.parent_state())
             .map_err(|e| Error::State(e.to_string()))?
-            .ok_or_else(|| Error::State("Miner actor not found".to_string()))?;
+            .ok_or_else(|| Error::State("Datacap actor not found".to_string()))?;
 
         let state = datacap::State::load(self.blockstore(), act.code, act.state)?;
 
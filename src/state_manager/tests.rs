@@ -0,0 +1,2044 @@
+// Copyright 2019-2024 ChainSafe Systems
+// SPDX-License-Identifier: Apache-2.0, MIT
+
+use super::*;
+use crate::blocks::{CachingBlockHeader, RawBlockHeader, Ticket, VRFProof};
+use crate::chain_sync::SyncConfig;
+use crate::db::{car::PlainCar, MemoryDB};
+use crate::networks::{self, ChainConfig, ACTOR_BUNDLES_METADATA};
+use crate::shim::machine::BuiltinActor;
+use crate::shim::state_tree::StateTreeVersion;
+use crate::test_utils::{builtin_actor, calibnet_v13_manifest};
+
+type TestDb = PlainCar<&'static [u8]>;
+
+/// Builds a [`StateManager`] on top of the calibnet genesis CAR, returning it
+/// along with the genesis [`Tipset`].
+fn calibnet_genesis() -> (Arc<StateManager<TestDb>>, Arc<Tipset>) {
+    calibnet_genesis_with_config(SyncConfig::default())
+}
+
+/// Like [`calibnet_genesis`], with the given sync configuration.
+fn calibnet_genesis_with_config(
+    sync_config: SyncConfig,
+) -> (Arc<StateManager<TestDb>>, Arc<Tipset>) {
+    let db = Arc::new(PlainCar::new(networks::calibnet::DEFAULT_GENESIS).unwrap());
+    calibnet_genesis_on(db, sync_config)
+}
+
+/// Like [`calibnet_genesis_with_config`], on top of `db`, which must hold the
+/// calibnet genesis.
+fn calibnet_genesis_on<DB: Blockstore + Send + Sync + 'static>(
+    db: Arc<DB>,
+    sync_config: SyncConfig,
+) -> (Arc<StateManager<DB>>, Arc<Tipset>) {
+    let genesis_header = db
+        .get_cbor_required(&networks::calibnet::GENESIS_CID)
+        .unwrap();
+    let chain_config = Arc::new(ChainConfig::calibnet());
+    let cs = Arc::new(
+        ChainStore::new(
+            db,
+            Arc::new(MemoryDB::default()),
+            Arc::new(MemoryDB::default()),
+            chain_config.clone(),
+            genesis_header,
+        )
+        .unwrap(),
+    );
+    let genesis = cs.heaviest_tipset();
+    let sm = StateManager::new(cs, chain_config, Arc::new(sync_config)).unwrap();
+    (Arc::new(sm), genesis)
+}
+
+/// Builds a tipset whose parent state only holds an init actor that assigns
+/// IDs to `addrs` in order.
+fn tipset_with_id_assignments(sm: &StateManager<TestDb>, addrs: &[Address]) -> Tipset {
+    let store = sm.blockstore_owned();
+    let mut init_state = fil_actor_init_state::v13::State::new(&store, "idtest".into()).unwrap();
+    for addr in addrs {
+        init_state
+            .map_addresses_to_id(&store, &addr.into(), None)
+            .unwrap();
+    }
+    let init_state_cid = store.put_cbor_default(&init_state).unwrap();
+
+    let mut tree = StateTree::new(store, StateTreeVersion::V5).unwrap();
+    tree.set_actor(
+        &Address::INIT_ACTOR,
+        ActorState::new(Cid::default(), init_state_cid, Zero::zero(), 0, None),
+    )
+    .unwrap();
+    Tipset::from(CachingBlockHeader::new(RawBlockHeader {
+        state_root: tree.flush().unwrap(),
+        ..Default::default()
+    }))
+}
+
+/// Builds a [`Tipset`] at `epoch` whose parent state only holds the given
+/// actors.
+fn tipset_with_actors(
+    sm: &StateManager<TestDb>,
+    epoch: ChainEpoch,
+    actors: impl IntoIterator<Item = (Address, ActorState)>,
+) -> Tipset {
+    let mut tree = StateTree::new(sm.blockstore_owned(), StateTreeVersion::V5).unwrap();
+    for (addr, actor) in actors {
+        tree.set_actor(&addr, actor).unwrap();
+    }
+    Tipset::from(CachingBlockHeader::new(RawBlockHeader {
+        epoch,
+        state_root: tree.flush().unwrap(),
+        ..Default::default()
+    }))
+}
+
+/// Loads the calibnet actor bundles into the store of `sm`, and returns the
+/// root of a state that the actors v13 can execute messages on. Besides the
+/// `accounts` with their balances, the state only holds the system actor and
+/// the actors the circulating supply is computed from. The bundles are
+/// downloaded, so tests using this are ignored by default.
+async fn state_with_bundle<DB: Blockstore + Send + Sync + 'static>(
+    sm: &StateManager<DB>,
+    accounts: impl IntoIterator<Item = (Address, TokenAmount)>,
+) -> Cid {
+    crate::daemon::bundle::load_actor_bundles(sm.blockstore(), &networks::NetworkChain::Calibnet)
+        .await
+        .unwrap();
+    let store = sm.blockstore();
+    let system = fil_actor_system_state::v13::State {
+        builtin_actors: calibnet_v13_manifest().source_cid(),
+    };
+    let account = |addr: Address, balance: TokenAmount| {
+        let state = fil_actor_account_state::v13::State {
+            address: addr.into(),
+        };
+        let mut actor = builtin_actor(sm.blockstore(), BuiltinActor::Account, &state);
+        actor.balance = balance.into();
+        actor
+    };
+    let mut tree = StateTree::new(sm.blockstore_owned(), StateTreeVersion::V5).unwrap();
+    for (addr, actor) in [
+        (
+            Address::SYSTEM_ACTOR,
+            builtin_actor(sm.blockstore(), BuiltinActor::System, &system),
+        ),
+        (
+            Address::REWARD_ACTOR,
+            builtin_actor(
+                sm.blockstore(),
+                BuiltinActor::Reward,
+                &fil_actor_reward_state::v13::State::new(Zero::zero()),
+            ),
+        ),
+        (
+            Address::POWER_ACTOR,
+            builtin_actor(
+                sm.blockstore(),
+                BuiltinActor::Power,
+                &fil_actor_power_state::v13::State::new(store).unwrap(),
+            ),
+        ),
+        (
+            Address::MARKET_ACTOR,
+            builtin_actor(
+                sm.blockstore(),
+                BuiltinActor::Market,
+                &fil_actor_market_state::v13::State::new(store).unwrap(),
+            ),
+        ),
+        (
+            Address::RESERVE_ACTOR,
+            account(Address::RESERVE_ACTOR, TokenAmount::zero()),
+        ),
+        (
+            Address::BURNT_FUNDS_ACTOR,
+            account(Address::BURNT_FUNDS_ACTOR, TokenAmount::zero()),
+        ),
+    ] {
+        tree.set_actor(&addr, actor).unwrap();
+    }
+    for (addr, balance) in accounts {
+        tree.set_actor(&addr, account(addr, balance)).unwrap();
+    }
+    tree.flush().unwrap()
+}
+
+/// Info of a miner whose owner and worker are `f0<id>`.
+fn miner_info(id: ActorID) -> fil_actor_miner_state::v13::MinerInfo {
+    fil_actor_miner_state::v13::MinerInfo::new(
+        id,
+        id,
+        vec![],
+        vec![],
+        vec![],
+        fvm_shared4::sector::RegisteredPoStProof::StackedDRGWindow32GiBV1P1,
+    )
+    .unwrap()
+}
+
+/// An empty miner state with the given info.
+fn miner_state(
+    sm: &StateManager<TestDb>,
+    info: &fil_actor_miner_state::v13::MinerInfo,
+) -> fil_actor_miner_state::v13::State {
+    let store = sm.blockstore_owned();
+    fil_actor_miner_state::v13::State::new(
+        &Policy::default(),
+        &store,
+        store.put_cbor_default(info).unwrap(),
+        0,
+        0,
+    )
+    .unwrap()
+}
+
+fn tipset_with_empty_market(sm: &StateManager<TestDb>) -> Tipset {
+    tipset_with_market(sm, &[], &[])
+}
+
+/// Builds a [`Tipset`] whose parent state holds a market actor with the given
+/// deal proposals and states.
+fn tipset_with_market(
+    sm: &StateManager<TestDb>,
+    proposals: &[(DealID, fil_actor_market_state::v13::DealProposal)],
+    states: &[(DealID, fil_actor_market_state::v13::DealState)],
+) -> Tipset {
+    let store = sm.blockstore_owned();
+    let mut market = fil_actor_market_state::v13::State::new(&store).unwrap();
+    market.put_deal_proposals(&store, proposals).unwrap();
+    market.put_deal_states(&store, states).unwrap();
+    tipset_with_actors(
+        sm,
+        0,
+        [(
+            Address::MARKET_ACTOR,
+            builtin_actor(sm.blockstore(), BuiltinActor::Market, &market),
+        )],
+    )
+}
+
+/// A deal proposal between client `f01000` and provider `f01001`.
+fn deal_proposal(label: &str) -> fil_actor_market_state::v13::DealProposal {
+    fil_actor_market_state::v13::DealProposal {
+        piece_cid: Cid::default(),
+        piece_size: fvm_shared4::piece::PaddedPieceSize(2048),
+        verified_deal: false,
+        client: fvm_shared4::address::Address::new_id(1000),
+        provider: fvm_shared4::address::Address::new_id(1001),
+        label: fil_actor_market_state::v13::Label::String(label.into()),
+        start_epoch: 10,
+        end_epoch: 100,
+        storage_price_per_epoch: fvm_shared4::econ::TokenAmount::from_atto(5),
+        provider_collateral: fvm_shared4::econ::TokenAmount::from_atto(7),
+        client_collateral: fvm_shared4::econ::TokenAmount::from_atto(3),
+    }
+}
+
+/// Builds a chain on top of `genesis` where a message is included at epoch 1
+/// and executed at epoch 2, emitting `events`, and sets it as the head.
+/// Returns the message CID along with the including and executing tipsets.
+fn chain_with_executed_message(
+    sm: &StateManager<TestDb>,
+    genesis: &Tipset,
+    events: &[StampedEvent],
+) -> (Cid, Arc<Tipset>, Arc<Tipset>) {
+    let (mut bls, _) = crate::test_utils::construct_messages();
+    bls.gas_fee_cap = TokenAmount::from_atto(150);
+    let msg_cid = sm.blockstore().put_cbor_default(&bls).unwrap();
+    let block = |epoch, parents: &Tipset, messages, message_receipts| {
+        let header = CachingBlockHeader::new(RawBlockHeader {
+            epoch,
+            parents: parents.key().clone(),
+            messages,
+            message_receipts,
+            parent_base_fee: TokenAmount::from_atto(100),
+            ..Default::default()
+        });
+        sm.blockstore().put_cbor_default(&header).unwrap();
+        Arc::new(Tipset::from(header))
+    };
+
+    let included = block(
+        1,
+        genesis,
+        TipsetValidator::compute_msg_root(sm.blockstore(), &[bls], &[]).unwrap(),
+        Cid::default(),
+    );
+    let receipt = fvm_shared4::receipt::Receipt {
+        exit_code: fvm_shared4::error::ExitCode::OK,
+        return_data: Default::default(),
+        gas_used: 10,
+        // Like the FVM, only compute the root of the events.
+        events_root: StampedEvent::store_events(&MemoryDB::default(), events).unwrap(),
+    };
+    let receipts = Amt::new_from_iter(sm.blockstore(), [receipt]).unwrap();
+    let executed = block(2, &included, Cid::default(), receipts);
+    sm.chain_store()
+        .set_heaviest_tipset(executed.clone())
+        .unwrap();
+    (msg_cid, included, executed)
+}
+
+#[test]
+fn eth_address_for_id_address() {
+    let (sm, _) = calibnet_genesis();
+    let ts = tipset_with_id_assignments(&sm, &[]);
+    let addr = Address::new_id(1000);
+
+    let eth_addr = sm.eth_address_for(&addr, &ts).unwrap().unwrap();
+    assert!(eth_addr.is_masked_id());
+    assert_eq!(eth_addr, EthAddress::from_actor_id(1000));
+
+    assert_eq!(sm.filecoin_address_for(&eth_addr, &ts).unwrap(), Some(addr));
+
+    // ID addresses aren't checked against the state, other addresses are.
+    assert!(sm.get_actor_at_tipset(&addr, &ts).unwrap().is_none());
+    let unassigned = Address::new_secp256k1(&[0x22; 65]).unwrap();
+    assert_eq!(sm.eth_address_for(&unassigned, &ts).unwrap(), None);
+}
+
+#[test]
+fn eth_address_for_delegated_address() {
+    let (sm, _) = calibnet_genesis();
+    let addr = Address::new_delegated(
+        Address::ETHEREUM_ACCOUNT_MANAGER_ACTOR.id().unwrap(),
+        &[0x11; 20],
+    )
+    .unwrap();
+
+    // `f410f` addresses are converted without consulting the state.
+    let ts = tipset_with_id_assignments(&sm, &[]);
+    let eth_addr = sm.eth_address_for(&addr, &ts).unwrap().unwrap();
+    assert!(!eth_addr.is_masked_id());
+    assert_eq!(eth_addr.to_filecoin_address().unwrap(), addr);
+    assert_eq!(sm.filecoin_address_for(&eth_addr, &ts).unwrap(), None);
+
+    let ts = tipset_with_id_assignments(&sm, &[addr]);
+    assert_eq!(
+        sm.filecoin_address_for(&eth_addr, &ts).unwrap(),
+        Some(Address::new_id(100))
+    );
+}
+
+#[test]
+fn get_actor_at_tipset() {
+    let (sm, genesis) = calibnet_genesis();
+
+    let reward = sm
+        .get_actor_at_tipset(&Address::REWARD_ACTOR, &genesis)
+        .unwrap()
+        .unwrap();
+    assert!(reward.balance.is_positive());
+
+    assert!(sm
+        .get_actor_at_tipset(&Address::new_id(u32::MAX as u64), &genesis)
+        .unwrap()
+        .is_none());
+}
+
+#[test]
+fn market_deal_not_found() {
+    let (sm, _) = calibnet_genesis();
+    let ts = tipset_with_empty_market(&sm);
+    assert!(sm.market_deal(0, &ts).unwrap().is_none());
+}
+
+#[test]
+fn market_deal_with_proposal_and_state() {
+    let (sm, _) = calibnet_genesis();
+    let ts = tipset_with_market(
+        &sm,
+        &[(4, deal_proposal("active")), (5, deal_proposal("pending"))],
+        &[(
+            4,
+            fil_actor_market_state::v13::DealState {
+                sector_number: 9,
+                sector_start_epoch: 20,
+                last_updated_epoch: 30,
+                slash_epoch: -1,
+            },
+        )],
+    );
+
+    let MarketDeal { proposal, state } = sm.market_deal(4, &ts).unwrap().unwrap();
+    assert_eq!(proposal.label, "active");
+    assert_eq!(proposal.client, Address::new_id(1000).into());
+    assert_eq!(proposal.provider, Address::new_id(1001).into());
+    assert_eq!(proposal.piece_size.0, 2048);
+    assert_eq!((proposal.start_epoch, proposal.end_epoch), (10, 100));
+    assert_eq!(proposal.storage_price_per_epoch.atto(), &5.into());
+    assert_eq!(state.sector_start_epoch, 20);
+    assert_eq!(state.last_updated_epoch, 30);
+    assert_eq!(state.slash_epoch, -1);
+
+    // A proposal without state hasn't been activated yet.
+    let MarketDeal { proposal, state } = sm.market_deal(5, &ts).unwrap().unwrap();
+    assert_eq!(proposal.label, "pending");
+    assert!(state == market::DealState::empty());
+}
+
+#[test]
+fn pending_deals_empty_market() {
+    let (sm, _) = calibnet_genesis();
+    let ts = tipset_with_empty_market(&sm);
+    assert!(sm.pending_deals(&ts).unwrap().is_empty());
+}
+
+#[test]
+fn pending_deals_skips_activated_deals() {
+    let (sm, _) = calibnet_genesis();
+    let activated = fil_actor_market_state::v13::DealState {
+        sector_number: 1,
+        sector_start_epoch: 20,
+        last_updated_epoch: -1,
+        slash_epoch: -1,
+    };
+    let ts = tipset_with_market(
+        &sm,
+        &[
+            (1, deal_proposal("activated")),
+            (2, deal_proposal("pending")),
+            (3, deal_proposal("also pending")),
+        ],
+        &[(1, activated)],
+    );
+
+    let pending = sm.pending_deals(&ts).unwrap();
+    assert_eq!(pending.keys().copied().sorted().collect_vec(), [2, 3]);
+    assert_eq!(pending[&2].proposal.label, "pending");
+    assert_eq!(pending[&3].proposal.label, "also pending");
+    assert!(pending
+        .values()
+        .all(|deal| deal.state == market::DealState::empty()));
+}
+
+#[test]
+fn allocations_by_client() {
+    let (sm, _) = calibnet_genesis();
+    let store = sm.blockstore_owned();
+    let allocation = |client, expiration| fil_actor_verifreg_state::v13::Allocation {
+        client,
+        provider: 1001,
+        data: Cid::default(),
+        size: fvm_shared4::piece::PaddedPieceSize(2048),
+        term_min: 100,
+        term_max: 200,
+        expiration,
+    };
+    let mut verifreg =
+        fil_actor_verifreg_state::v13::State::new(&store, Address::new_id(80).into()).unwrap();
+    let ids = verifreg
+        .insert_allocations(
+            &store,
+            1000,
+            vec![allocation(1000, 10), allocation(1000, 20)],
+        )
+        .unwrap();
+    verifreg
+        .insert_allocations(&store, 1002, vec![allocation(1002, 30)])
+        .unwrap();
+    let ts = tipset_with_actors(
+        &sm,
+        0,
+        [(
+            Address::VERIFIED_REGISTRY_ACTOR,
+            builtin_actor(sm.blockstore(), BuiltinActor::VerifiedRegistry, &verifreg),
+        )],
+    );
+
+    let allocations = sm.allocations(&Address::new_id(1000), &ts).unwrap();
+    assert_eq!(allocations.keys().copied().sorted().collect_vec(), ids);
+    assert_eq!(
+        ids.iter()
+            .map(|id| allocations[id].expiration)
+            .collect_vec(),
+        [10, 20]
+    );
+    assert!(allocations
+        .values()
+        .all(|it| it.client == 1000 && it.provider == 1001));
+    assert_eq!(
+        sm.allocations(&Address::new_id(1002), &ts).unwrap().len(),
+        1
+    );
+    assert!(sm
+        .allocations(&Address::new_id(1003), &ts)
+        .unwrap()
+        .is_empty());
+}
+
+#[test]
+fn sector_info_matches_all_sectors() {
+    let (sm, _) = calibnet_genesis();
+    let store = sm.blockstore_owned();
+    let mut miner_state = miner_state(&sm, &miner_info(1000));
+    let sectors = [1, 3].map(
+        |sector_number| fil_actor_miner_state::v13::SectorOnChainInfo {
+            sector_number,
+            ..Default::default()
+        },
+    );
+    miner_state.put_sectors(&store, sectors.into()).unwrap();
+    let miner = Address::new_id(1001);
+    let ts = tipset_with_actors(
+        &sm,
+        0,
+        [(
+            miner,
+            builtin_actor(sm.blockstore(), BuiltinActor::Miner, &miner_state),
+        )],
+    );
+
+    let sectors = sm.get_all_sectors(&miner, &ts).unwrap();
+    assert_eq!(sectors.len(), 2);
+    for sector in &sectors {
+        assert_eq!(
+            sm.sector_info(&miner, sector.sector_number, &ts)
+                .unwrap()
+                .as_ref(),
+            Some(sector)
+        );
+    }
+    assert!(sm.sector_info(&miner, 2, &ts).unwrap().is_none());
+}
+
+#[test]
+fn miner_beneficiary_reads_miner_info() {
+    let (sm, _) = calibnet_genesis();
+    let mut info = miner_info(1000);
+    info.beneficiary = Address::new_id(2000).into();
+    info.beneficiary_term = fil_actor_miner_state::v13::BeneficiaryTerm {
+        quota: TokenAmount::from_whole(10).into(),
+        used_quota: TokenAmount::from_whole(1).into(),
+        expiration: 500,
+    };
+    let miner_state = miner_state(&sm, &info);
+    let miner = Address::new_id(1001);
+    let ts = tipset_with_actors(
+        &sm,
+        1_493_854,
+        [(
+            miner,
+            builtin_actor(sm.blockstore(), BuiltinActor::Miner, &miner_state),
+        )],
+    );
+
+    let beneficiary = sm.miner_beneficiary(&miner, &ts).unwrap();
+    assert_eq!(beneficiary.beneficiary, Address::new_id(2000));
+    assert_eq!(
+        TokenAmount::from(beneficiary.term.quota),
+        TokenAmount::from_whole(10)
+    );
+    assert_eq!(
+        TokenAmount::from(beneficiary.term.used_quota),
+        TokenAmount::from_whole(1)
+    );
+    assert_eq!(beneficiary.term.expiration, 500);
+    assert!(beneficiary.pending.is_none());
+}
+
+#[test]
+fn vm_circulating_supply_is_cached() {
+    let (sm, _) = calibnet_genesis();
+    let store = sm.blockstore_owned();
+    let reward = fil_actor_reward_state::v13::State::new(Zero::zero());
+    let power = fil_actor_power_state::v13::State::new(&store).unwrap();
+    let market = fil_actor_market_state::v13::State::new(&store).unwrap();
+    let ts = tipset_with_actors(
+        &sm,
+        0,
+        [
+            (
+                Address::REWARD_ACTOR,
+                builtin_actor(sm.blockstore(), BuiltinActor::Reward, &reward),
+            ),
+            (
+                Address::POWER_ACTOR,
+                builtin_actor(sm.blockstore(), BuiltinActor::Power, &power),
+            ),
+            (
+                Address::MARKET_ACTOR,
+                builtin_actor(sm.blockstore(), BuiltinActor::Market, &market),
+            ),
+            (
+                Address::BURNT_FUNDS_ACTOR,
+                ActorState::new(Cid::default(), Cid::default(), Zero::zero(), 0, None),
+            ),
+        ],
+    );
+    let key = (ts.epoch(), *ts.parent_state());
+    assert!(!sm.circ_supply_cache.lock().contains(&key));
+
+    let supply = sm
+        .get_vm_circulating_supply(ts.epoch(), ts.parent_state())
+        .unwrap();
+    assert_eq!(sm.circ_supply_cache.lock().peek(&key), Some(&supply));
+
+    let cached = sm
+        .get_vm_circulating_supply(ts.epoch(), ts.parent_state())
+        .unwrap();
+    assert_eq!(supply, cached);
+    assert_eq!(sm.circ_supply_cache.lock().len(), 1);
+}
+
+#[test]
+fn lookup_id_is_scoped_to_tipset() {
+    let (sm, _) = calibnet_genesis();
+    let addr = Address::new_actor(b"reorged");
+    let other = Address::new_actor(b"other");
+
+    let ts = tipset_with_id_assignments(&sm, &[addr]);
+    // A competing fork where another actor was created first.
+    let reorged_ts = tipset_with_id_assignments(&sm, &[other, addr]);
+    assert_ne!(ts.key(), reorged_ts.key());
+
+    let id = sm.lookup_id(&addr, &ts).unwrap().unwrap();
+    let reorged_id = sm.lookup_id(&addr, &reorged_ts).unwrap().unwrap();
+    assert_ne!(id, reorged_id);
+    assert_eq!(sm.lookup_id(&other, &ts).unwrap(), None);
+
+    // Cached lookups keep returning the value for their own tipset.
+    assert_eq!(sm.lookup_id(&addr, &ts).unwrap(), Some(id));
+    assert_eq!(sm.lookup_id(&addr, &reorged_ts).unwrap(), Some(reorged_id));
+}
+
+#[test]
+fn total_burned_accumulates_base_fee_burns() {
+    let (sm, genesis) = calibnet_genesis();
+    let (bls, _) = crate::test_utils::construct_messages();
+    // Each tipset includes a message, executed by the next one with 10 more
+    // gas used at a base fee 100 higher. The fee cap of 150 caps the burn
+    // from the second message on.
+    let mut tipsets = vec![];
+    let mut parent = genesis.as_ref().clone();
+    for epoch in 1..=4 {
+        let mut message = bls.clone();
+        message.sequence = epoch as u64;
+        message.gas_fee_cap = TokenAmount::from_atto(150);
+        sm.blockstore().put_cbor_default(&message).unwrap();
+        let receipts = (epoch > 1).then(|| fvm_shared4::receipt::Receipt {
+            exit_code: fvm_shared4::error::ExitCode::OK,
+            return_data: Default::default(),
+            gas_used: 10 * (epoch as u64 - 1),
+            events_root: None,
+        });
+        let header = CachingBlockHeader::new(RawBlockHeader {
+            epoch,
+            parents: parent.key().clone(),
+            messages: TipsetValidator::compute_msg_root(sm.blockstore(), &[message], &[]).unwrap(),
+            message_receipts: Amt::new_from_iter(sm.blockstore(), receipts).unwrap(),
+            parent_base_fee: TokenAmount::from_atto(100 * epoch),
+            ..Default::default()
+        });
+        sm.blockstore().put_cbor_default(&header).unwrap();
+        parent = Tipset::from(header);
+        tipsets.push(parent.clone());
+    }
+
+    let totals = [
+        0,
+        100 * 10,
+        100 * 10 + 150 * 20,
+        100 * 10 + 150 * 20 + 150 * 30,
+    ];
+    // Computed from genesis for the head, then read back for its ancestors.
+    assert_eq!(
+        sm.total_burned(&tipsets[3]).unwrap(),
+        TokenAmount::from_atto(totals[3])
+    );
+    for (ts, total) in tipsets.iter().zip(totals) {
+        assert_eq!(sm.total_burned(ts).unwrap(), TokenAmount::from_atto(total));
+    }
+}
+
+#[test]
+fn base_fee_burned_by_executed_message() {
+    let (sm, genesis) = calibnet_genesis();
+    assert!(sm.base_fee_burned(&genesis).unwrap().is_zero());
+
+    // The message uses 10 gas at a base fee of 100, below its fee cap.
+    let (_, _, executed) = chain_with_executed_message(&sm, &genesis, &[]);
+    assert_eq!(
+        sm.base_fee_burned(&executed).unwrap(),
+        TokenAmount::from_atto(10 * 100)
+    );
+}
+
+#[tokio::test]
+async fn fee_history_skips_genesis() {
+    let (sm, genesis) = calibnet_genesis();
+    assert!(sm.fee_history(10, genesis).await.unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn fee_history_reports_base_fees_and_gas_used() {
+    let (sm, genesis) = calibnet_genesis();
+    let mut head = genesis;
+    for (epoch, base_fee, gas_used) in [
+        (1, 100, vec![2_500_000_000]),
+        (2, 200, vec![1_000_000_000, 4_000_000_000]),
+        (3, 150, vec![]),
+    ] {
+        let header = CachingBlockHeader::new(RawBlockHeader {
+            epoch,
+            parents: head.key().clone(),
+            parent_base_fee: TokenAmount::from_atto(base_fee),
+            ..Default::default()
+        });
+        sm.blockstore().put_cbor_default(&header).unwrap();
+        head = Arc::new(Tipset::from(header));
+        // Stand in for executing the tipset.
+        let receipts = Amt::new_from_iter(
+            sm.blockstore(),
+            gas_used
+                .into_iter()
+                .map(|gas_used| fvm_shared4::receipt::Receipt {
+                    exit_code: fvm_shared4::error::ExitCode::OK,
+                    return_data: Default::default(),
+                    gas_used,
+                    events_root: None,
+                }),
+        )
+        .unwrap();
+        sm.cache
+            .insert(head.key().clone(), (*head.parent_state(), receipts));
+    }
+
+    let history = sm.fee_history(2, head.clone()).await.unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|sample| (sample.epoch, sample.base_fee.clone(), sample.gas_used_ratio))
+            .collect_vec(),
+        [
+            (2, TokenAmount::from_atto(200), 0.5),
+            (3, TokenAmount::from_atto(150), 0.0),
+        ]
+    );
+    let history = sm.fee_history(10, head).await.unwrap();
+    assert_eq!(
+        history
+            .iter()
+            .map(|sample| sample.gas_used_ratio)
+            .collect_vec(),
+        [0.25, 0.5, 0.0]
+    );
+}
+
+#[test]
+fn actor_code_cids_for_network_version() {
+    let (sm, _) = calibnet_genesis();
+    let bundle = &ACTOR_BUNDLES_METADATA
+        .get(&(networks::NetworkChain::Calibnet, "v13.0.0".into()))
+        .unwrap()
+        .manifest;
+
+    let code_cids = sm.actor_code_cids(NetworkVersion::V22).unwrap();
+    assert_eq!(code_cids.len(), bundle.builtin_actors().len());
+    assert_eq!(code_cids.get("system"), Some(&bundle.get_system()));
+    assert_eq!(code_cids.get("init"), Some(&bundle.get_init()));
+
+    // Network versions before actors v8 have no bundle.
+    assert!(sm.actor_code_cids(NetworkVersion::V15).is_err());
+}
+
+#[test]
+fn verify_actor_code_cids_against_bundle() {
+    let (sm, genesis) = calibnet_genesis();
+    let bundle = &ACTOR_BUNDLES_METADATA
+        .get(&(networks::NetworkChain::Calibnet, "v13.0.0".into()))
+        .unwrap()
+        .manifest;
+    let epoch = sm.chain_config().epoch(networks::Height::DragonFix);
+    // Builds a tipset whose system actor references a manifest of `actors`.
+    let tipset_with_manifest = |actors: Vec<(String, Cid)>| {
+        let actor_list = sm.blockstore().put_cbor_default(&actors).unwrap();
+        let manifest = sm.blockstore().put_cbor_default(&(1, actor_list)).unwrap();
+        let system = fil_actor_system_state::v13::State {
+            builtin_actors: manifest,
+        };
+        tipset_with_actors(
+            &sm,
+            epoch,
+            [(
+                Address::SYSTEM_ACTOR,
+                builtin_actor(sm.blockstore(), BuiltinActor::System, &system),
+            )],
+        )
+    };
+    let actors = || {
+        bundle
+            .builtin_actors()
+            .map(|(actor, cid)| (actor.name().to_string(), cid))
+            .collect_vec()
+    };
+
+    sm.verify_actor_code_cids(&tipset_with_manifest(actors()))
+        .unwrap();
+
+    let mut swapped = actors();
+    let (_, init) = swapped.iter_mut().find(|(name, _)| name == "init").unwrap();
+    *init = *genesis.parent_state();
+    assert!(sm
+        .verify_actor_code_cids(&tipset_with_manifest(swapped))
+        .is_err());
+
+    // The calibnet genesis predates actor bundles.
+    assert!(sm.verify_actor_code_cids(&genesis).is_err());
+}
+
+#[test]
+fn diff_state_roots_lists_changed_actor() {
+    let (sm, _) = calibnet_genesis();
+    let store = sm.blockstore_owned();
+    let actor = |balance: u64| {
+        ActorState::new(
+            Cid::default(),
+            Cid::default(),
+            TokenAmount::from_atto(balance),
+            0,
+            None,
+        )
+    };
+
+    let mut tree = StateTree::new(store.clone(), StateTreeVersion::V5).unwrap();
+    tree.set_actor(&Address::new_id(1000), actor(1)).unwrap();
+    tree.set_actor(&Address::new_id(1001), actor(1)).unwrap();
+    let root_a = tree.flush().unwrap();
+
+    tree.set_actor(&Address::new_id(1001), actor(2)).unwrap();
+    let root_b = tree.flush().unwrap();
+
+    assert!(sm.diff_state_roots(&root_a, &root_a).unwrap().is_empty());
+    assert_eq!(
+        sm.diff_state_roots(&root_a, &root_b).unwrap(),
+        vec![ActorDiff {
+            address: Address::new_id(1001),
+            old: Some(actor(1)),
+            new: Some(actor(2)),
+        }]
+    );
+}
+
+#[test]
+fn actor_is_type_checks_code_cid() {
+    let (sm, _) = calibnet_genesis();
+    let miner = Address::new_id(1000);
+    let ts = tipset_with_actors(
+        &sm,
+        1_493_854,
+        [(
+            miner,
+            builtin_actor(sm.blockstore(), BuiltinActor::Miner, &()),
+        )],
+    );
+
+    assert!(sm.actor_is_type(&miner, BuiltinActor::Miner, &ts).unwrap());
+    assert!(!sm
+        .actor_is_type(&miner, BuiltinActor::Multisig, &ts)
+        .unwrap());
+    assert!(!sm
+        .actor_is_type(&Address::new_id(1001), BuiltinActor::Miner, &ts)
+        .unwrap());
+}
+
+#[test]
+fn account_pubkey_reads_account_state() {
+    let (sm, _) = calibnet_genesis();
+    let store = sm.blockstore_owned();
+    let bls_key = bls_signatures::PrivateKey::new([1; 32]).public_key();
+    let bls = Address::new_bls(&bls_key.as_bytes()).unwrap();
+    let secp = Address::new_secp256k1(&[8; 65]).unwrap();
+    let multisig = Address::new_actor(b"multisig");
+    let unknown = Address::new_bls(
+        &bls_signatures::PrivateKey::new([2; 32])
+            .public_key()
+            .as_bytes(),
+    )
+    .unwrap();
+
+    let mut init_state =
+        fil_actor_init_state::v13::State::new(&store, "pubkeytest".into()).unwrap();
+    let [bls_id, secp_id, multisig_id] = [bls, secp, multisig].map(|addr| {
+        let (id, _) = init_state
+            .map_addresses_to_id(&store, &addr.into(), None)
+            .unwrap();
+        Address::new_id(id)
+    });
+    let miner = Address::new_id(1001);
+    let account_state = |address: Address| fil_actor_account_state::v13::State {
+        address: address.into(),
+    };
+    let ts = tipset_with_actors(
+        &sm,
+        1_493_854,
+        [
+            (
+                Address::INIT_ACTOR,
+                builtin_actor(sm.blockstore(), BuiltinActor::Init, &init_state),
+            ),
+            (
+                bls_id,
+                builtin_actor(sm.blockstore(), BuiltinActor::Account, &account_state(bls)),
+            ),
+            (
+                secp_id,
+                builtin_actor(sm.blockstore(), BuiltinActor::Account, &account_state(secp)),
+            ),
+            (
+                multisig_id,
+                builtin_actor(sm.blockstore(), BuiltinActor::Multisig, &()),
+            ),
+            (
+                miner,
+                builtin_actor(sm.blockstore(), BuiltinActor::Miner, &()),
+            ),
+        ],
+    );
+
+    for addr in [bls_id, bls] {
+        assert_eq!(
+            sm.account_pubkey(&addr, &ts).unwrap(),
+            Some(AccountKey::Bls(bls_key))
+        );
+    }
+    for addr in [secp_id, secp] {
+        assert_eq!(
+            sm.account_pubkey(&addr, &ts).unwrap(),
+            Some(AccountKey::Secp256k1(secp))
+        );
+    }
+    for addr in [miner, multisig, multisig_id, unknown, Address::new_id(1002)] {
+        assert_eq!(sm.account_pubkey(&addr, &ts).unwrap(), None, "{addr}");
+    }
+}
+
+#[test]
+fn state_stats_counts_actors_by_type() {
+    let (sm, _) = calibnet_genesis();
+    let account_state = |key| fil_actor_account_state::v13::State {
+        address: Address::new_bls(&[key; 48]).unwrap().into(),
+    };
+    let (a, b) = (account_state(1), account_state(2));
+    let ts = tipset_with_actors(
+        &sm,
+        1_493_854,
+        [
+            (
+                Address::new_id(1000),
+                builtin_actor(sm.blockstore(), BuiltinActor::Account, &a),
+            ),
+            (
+                Address::new_id(1001),
+                builtin_actor(sm.blockstore(), BuiltinActor::Account, &b),
+            ),
+            (
+                Address::new_id(1002),
+                builtin_actor(sm.blockstore(), BuiltinActor::Miner, &()),
+            ),
+            // Shares its state with an account.
+            (
+                Address::new_id(1003),
+                builtin_actor(sm.blockstore(), BuiltinActor::Miner, &a),
+            ),
+        ],
+    );
+
+    let account_bytes = to_vec(&a).unwrap().len() + to_vec(&b).unwrap().len();
+    let miner_bytes = to_vec(&()).unwrap().len() + to_vec(&a).unwrap().len();
+    let stats = sm.state_stats(&ts).unwrap();
+    assert_eq!(stats.actors, 4);
+    assert_eq!(
+        stats.state_bytes,
+        account_bytes + to_vec(&()).unwrap().len()
+    );
+    assert_eq!(
+        stats.by_actor_type,
+        HashMap::from_iter([
+            (
+                "account".to_string(),
+                ActorTypeStats {
+                    actors: 2,
+                    state_bytes: account_bytes,
+                }
+            ),
+            (
+                "storageminer".to_string(),
+                ActorTypeStats {
+                    actors: 2,
+                    state_bytes: miner_bytes,
+                }
+            ),
+        ])
+    );
+    assert_eq!(stats.hamt_depth, 1);
+}
+
+#[tokio::test]
+async fn tipset_state_samples_traces() {
+    let (sm, genesis) = calibnet_genesis_with_config(SyncConfig {
+        trace_sample_rate: 0.0,
+        ..Default::default()
+    });
+    sm.tipset_state(&genesis).await.unwrap();
+    assert!(sm.trace_samples().is_empty());
+
+    let (sm, genesis) = calibnet_genesis_with_config(SyncConfig {
+        trace_sample_rate: 1.0,
+        ..Default::default()
+    });
+    sm.tipset_state(&genesis).await.unwrap();
+    let samples = sm.trace_samples();
+    assert_eq!(samples.len(), 1);
+    assert_eq!(samples[0].epoch, 0);
+    assert_eq!(&samples[0].key, genesis.key());
+    // The genesis tipset has no messages to trace.
+    assert!(samples[0].invocations.is_empty());
+
+    // A cached state is not sampled again.
+    sm.tipset_state(&genesis).await.unwrap();
+    assert_eq!(sm.trace_samples().len(), 1);
+}
+
+#[test]
+fn trace_samples_keep_invocation_results() {
+    let (sm, genesis) = calibnet_genesis();
+    let (msg_cid, included, executed) = chain_with_executed_message(&sm, &genesis, &[]);
+
+    // Stand in for the result the VM gives for the transfer.
+    let msg: Message = sm.blockstore().get_cbor_required(&msg_cid).unwrap();
+    let Receipt::V4(msg_receipt) = Receipt::get_receipt(
+        sm.blockstore(),
+        &executed.min_ticket_block().message_receipts,
+        0,
+    )
+    .unwrap()
+    .unwrap() else {
+        panic!("expected a v4 receipt");
+    };
+    let apply_ret = ApplyRet::from(fvm4::executor::ApplyRet {
+        msg_receipt,
+        penalty: Default::default(),
+        miner_tip: Default::default(),
+        base_fee_burn: Default::default(),
+        over_estimation_burn: Default::default(),
+        refund: Default::default(),
+        gas_refund: 0,
+        gas_burned: 0,
+        failure_info: None,
+        exec_trace: vec![
+            fvm4::trace::ExecutionEvent::Call {
+                from: 100,
+                to: *msg.to,
+                method: msg.method_num,
+                params: None,
+                value: msg.value.clone().into(),
+                gas_limit: msg.gas_limit,
+                read_only: false,
+            },
+            fvm4::trace::ExecutionEvent::CallReturn(fvm_shared4::error::ExitCode::OK, None),
+        ],
+        events: vec![],
+    });
+    let message = ChainMessage::Unsigned(msg.clone());
+    let ctx = MessageCallbackCtx {
+        cid: msg_cid,
+        message: &message,
+        apply_ret: &apply_ret,
+        at: CalledAt::Applied,
+        duration: Default::default(),
+    };
+    sm.push_trace_sample(&included, vec![invocation_result(&ctx).unwrap()]);
+
+    let samples = sm.trace_samples();
+    assert_eq!(samples.len(), 1);
+    assert_eq!(&samples[0].key, included.key());
+    let [invocation] = samples[0].invocations.as_slice() else {
+        panic!("expected one invocation");
+    };
+    assert_eq!(invocation.msg_cid, msg_cid);
+    assert_eq!(invocation.msg_rct.as_ref().unwrap().gas_used(), 10);
+    let trace = invocation.execution_trace.as_ref().unwrap();
+    assert_eq!(trace.msg.to, msg.to);
+    assert_eq!(trace.msg.value, msg.value);
+    assert!(trace.subcalls.is_empty());
+}
+
+#[test]
+fn verify_block_message_roots_detects_tampered_cid() {
+    use crate::utils::cid::CidCborExt as _;
+
+    let (sm, _) = calibnet_genesis();
+    let header_with = |bls: &[Message], secp: &[crate::message::SignedMessage]| {
+        CachingBlockHeader::new(RawBlockHeader {
+            messages: TipsetValidator::compute_msg_root(sm.blockstore(), bls, secp).unwrap(),
+            ..Default::default()
+        })
+    };
+    let (bls, secp) = crate::test_utils::construct_messages();
+
+    sm.blockstore().put_cbor_default(&secp).unwrap();
+    let header = header_with(&[], &[secp]);
+    sm.verify_block_message_roots(&header).unwrap();
+
+    // Persist a different message under the CID of `bls`.
+    let bls_cid = Cid::from_cbor_blake2b256(&bls).unwrap();
+    let mut other = bls.clone();
+    other.sequence += 1;
+    sm.blockstore()
+        .put_keyed(&bls_cid, &fvm_ipld_encoding::to_vec(&other).unwrap())
+        .unwrap();
+    let header = header_with(&[bls], &[]);
+    assert!(sm.verify_block_message_roots(&header).is_err());
+}
+
+#[test]
+fn all_power_claims_lists_every_miner() {
+    use fil_actor_power_state::v13::{set_claim, Claim};
+    use num_traits::ToPrimitive as _;
+
+    let (sm, _) = calibnet_genesis();
+    let store = sm.blockstore_owned();
+    let mut power = fil_actor_power_state::v13::State::new(&store).unwrap();
+    let mut claims = power.load_claims(&store).unwrap();
+    for (id, raw, qa) in [(1000, 32, 320), (1001, 64, 64)] {
+        let claim = Claim {
+            window_post_proof_type:
+                fvm_shared4::sector::RegisteredPoStProof::StackedDRGWindow32GiBV1P1,
+            raw_byte_power: raw.into(),
+            quality_adj_power: qa.into(),
+        };
+        set_claim(&mut claims, &Address::new_id(id).into(), claim).unwrap();
+    }
+    power.save_claims(&mut claims).unwrap();
+    let ts = tipset_with_actors(
+        &sm,
+        0,
+        [(
+            Address::POWER_ACTOR,
+            builtin_actor(sm.blockstore(), BuiltinActor::Power, &power),
+        )],
+    );
+
+    let mut claims = sm
+        .all_power_claims(&ts)
+        .unwrap()
+        .into_iter()
+        .map(|(addr, claim)| {
+            (
+                addr,
+                claim.raw_byte_power.to_u64().unwrap(),
+                claim.quality_adj_power.to_u64().unwrap(),
+            )
+        })
+        .collect::<Vec<_>>();
+    claims.sort();
+    assert_eq!(
+        claims,
+        [
+            (Address::new_id(1000), 32, 320),
+            (Address::new_id(1001), 64, 64)
+        ]
+    );
+}
+
+#[test]
+fn msig_pending_lists_proposed_transactions() {
+    use fil_actors_shared::v13::{Map2, DEFAULT_HAMT_CONFIG};
+    use fvm_ipld_encoding::RawBytes;
+    use fvm_shared4::{address::Address as Address4, econ::TokenAmount as TokenAmount4};
+
+    // Multisig actor types are not a direct dependency, so the v13
+    // `Transaction` and `State` are encoded through their tuple layout.
+    type TxnTuple = (Address4, TokenAmount4, u64, RawBytes, Vec<Address4>);
+
+    let (sm, _) = calibnet_genesis();
+    let store = sm.blockstore_owned();
+    let signers = [Address::new_id(1000), Address::new_id(1001)];
+    let mut txns = Map2::<_, i64, TxnTuple>::empty(&store, DEFAULT_HAMT_CONFIG, "pending txns");
+    txns.set(
+        &7,
+        (
+            Address::new_id(2000).into(),
+            TokenAmount::from_whole(3).into(),
+            0,
+            RawBytes::default(),
+            vec![signers[0].into()],
+        ),
+    )
+    .unwrap();
+    let msig = (
+        signers.map(Address4::from).to_vec(),
+        2_u64,
+        8_i64,
+        TokenAmount4::default(),
+        0 as ChainEpoch,
+        0 as ChainEpoch,
+        txns.flush().unwrap(),
+    );
+    let addr = Address::new_id(1100);
+    let ts = tipset_with_actors(
+        &sm,
+        0,
+        [(
+            addr,
+            builtin_actor(sm.blockstore(), BuiltinActor::Multisig, &msig),
+        )],
+    );
+
+    let pending = sm.msig_pending(&addr, &ts).unwrap();
+    assert_eq!(pending.len(), 1);
+    let txn = &pending[0];
+    assert_eq!(txn.id, 7);
+    assert_eq!(txn.to, Address::new_id(2000));
+    assert_eq!(txn.value, TokenAmount::from_whole(3));
+    assert_eq!(txn.method, 0);
+    assert_eq!(txn.approved, vec![signers[0]]);
+}
+
+#[test]
+fn verified_client_status_reads_datacap_balance() {
+    let (sm, _) = calibnet_genesis();
+    let store = sm.blockstore_owned();
+    let mut datacap =
+        fil_actor_datacap_state::v13::State::new(&store, Address::VERIFIED_REGISTRY_ACTOR.into())
+            .unwrap();
+    datacap
+        .token
+        .change_balance_by(&store, 1000, &TokenAmount::from_whole(42).into())
+        .unwrap();
+    let ts = tipset_with_actors(
+        &sm,
+        1_493_854,
+        [(
+            Address::DATACAP_TOKEN_ACTOR,
+            builtin_actor(sm.blockstore(), BuiltinActor::DataCap, &datacap),
+        )],
+    );
+
+    assert_eq!(
+        sm.verified_client_status(&Address::new_id(1000), &ts)
+            .unwrap(),
+        Some(42.into())
+    );
+    assert_eq!(
+        sm.verified_client_status(&Address::new_id(1001), &ts)
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn datacap_balance_reads_datacap_actor() {
+    let (sm, _) = calibnet_genesis();
+    let store = sm.blockstore_owned();
+    let mut datacap =
+        fil_actor_datacap_state::v13::State::new(&store, Address::VERIFIED_REGISTRY_ACTOR.into())
+            .unwrap();
+    for (holder, amount) in [(1000, 42), (1002, 7)] {
+        datacap
+            .token
+            .change_balance_by(&store, holder, &TokenAmount::from_whole(amount).into())
+            .unwrap();
+    }
+    let ts = tipset_with_actors(
+        &sm,
+        1_493_854,
+        [(
+            Address::DATACAP_TOKEN_ACTOR,
+            builtin_actor(sm.blockstore(), BuiltinActor::DataCap, &datacap),
+        )],
+    );
+
+    let balance = |id| sm.datacap_balance(&Address::new_id(id), &ts).unwrap();
+    assert_eq!(balance(1000), Some(42.into()));
+    assert_eq!(balance(1002), Some(7.into()));
+    assert_eq!(balance(1001), None);
+}
+
+#[test]
+fn search_message_is_bounded_by_epoch_range() {
+    let (sm, genesis) = calibnet_genesis();
+    let (msg_cid, _, executed) = chain_with_executed_message(&sm, &genesis, &[]);
+
+    let lookup = sm.search_message(msg_cid, 1, 2).unwrap().unwrap();
+    assert_eq!(lookup.tipset, *executed.key());
+    assert_eq!(lookup.height, 2);
+    assert_eq!(lookup.receipt.gas_used(), 10);
+    assert_eq!(lookup.message, msg_cid);
+
+    assert!(sm.search_message(msg_cid, 0, 1).unwrap().is_none());
+
+    // Upper bounds past the head are capped, and empty ranges match nothing.
+    let lookup = sm.search_message(msg_cid, 1, 100).unwrap().unwrap();
+    assert_eq!(lookup.tipset, *executed.key());
+    assert!(sm.search_message(msg_cid, 3, 100).unwrap().is_none());
+    assert!(sm.search_message(msg_cid, 2, 1).unwrap().is_none());
+}
+
+#[test]
+fn miner_available_balance_excludes_locked_funds() {
+    let (sm, _) = calibnet_genesis();
+    let mut miner_state = miner_state(&sm, &miner_info(1000));
+    miner_state.locked_funds = TokenAmount::from_whole(10).into();
+    miner_state.pre_commit_deposits = TokenAmount::from_whole(20).into();
+    miner_state.initial_pledge = TokenAmount::from_whole(30).into();
+    miner_state.fee_debt = TokenAmount::from_whole(5).into();
+    let miner = Address::new_id(1001);
+    let mut actor = builtin_actor(sm.blockstore(), BuiltinActor::Miner, &miner_state);
+    actor.balance = TokenAmount::from_whole(100).into();
+    let ts = tipset_with_actors(&sm, 1_493_854, [(miner, actor)]);
+
+    assert_eq!(
+        sm.miner_available_balance(&miner, &ts).unwrap(),
+        TokenAmount::from_whole(35)
+    );
+}
+
+#[tokio::test]
+#[ignore = "downloads the actor bundles"]
+async fn replay_message_uses_inclusion_tipset() {
+    let (sm, genesis) = calibnet_genesis();
+    let (from, to) = (Address::new_id(1000), Address::new_id(1001));
+    let state_root = state_with_bundle(
+        &sm,
+        [
+            (from, TokenAmount::from_whole(1)),
+            (to, TokenAmount::zero()),
+        ],
+    )
+    .await;
+    let transfer = Message {
+        gas_limit: 10_000_000,
+        gas_fee_cap: TokenAmount::from_atto(150),
+        gas_premium: TokenAmount::from_atto(10),
+        ..Message::transfer(from, to, TokenAmount::from_atto(42))
+    };
+    let msg_cid = sm.blockstore().put_cbor_default(&transfer).unwrap();
+
+    // Execution starts right after the parent epoch, so no cron or
+    // migrations run before the message.
+    let epoch = sm.chain_config().epoch(networks::Height::DragonFix) + 10;
+    let block = |epoch, parents: &Tipset, state_root, messages, message_receipts| {
+        let header = CachingBlockHeader::new(RawBlockHeader {
+            epoch,
+            parents: parents.key().clone(),
+            state_root,
+            messages,
+            message_receipts,
+            parent_base_fee: TokenAmount::from_atto(100),
+            timestamp: genesis.min_timestamp(),
+            ..Default::default()
+        });
+        sm.blockstore().put_cbor_default(&header).unwrap();
+        Arc::new(Tipset::from(header))
+    };
+    let empty = TipsetValidator::compute_msg_root(sm.blockstore(), &[], &[]).unwrap();
+    let parent = block(epoch - 1, &genesis, state_root, empty, Cid::default());
+    let included = block(
+        epoch,
+        &parent,
+        state_root,
+        TipsetValidator::compute_msg_root(sm.blockstore(), &[transfer.clone()], &[]).unwrap(),
+        Cid::default(),
+    );
+    let receipt = fvm_shared4::receipt::Receipt {
+        exit_code: fvm_shared4::error::ExitCode::OK,
+        return_data: Default::default(),
+        gas_used: 0,
+        events_root: None,
+    };
+    let receipts = Amt::new_from_iter(sm.blockstore(), [receipt]).unwrap();
+    let executed = block(epoch + 1, &included, state_root, empty, receipts);
+    sm.chain_store().set_heaviest_tipset(executed).unwrap();
+
+    assert_eq!(
+        sm.message_inclusion_tipset(msg_cid).await.unwrap(),
+        included
+    );
+    assert!(sm.message_inclusion_tipset(Cid::default()).await.is_err());
+
+    let result = sm.replay_message(msg_cid).await.unwrap();
+    assert_eq!(result.msg_cid, msg_cid);
+    let receipt = result.msg_rct.unwrap();
+    assert!(receipt.exit_code().is_success());
+    assert!(receipt.gas_used() > 0);
+    assert!(receipt.gas_used() < transfer.gas_limit);
+
+    // The base fee is below the fee cap, so the whole premium goes to the
+    // miner and the rest of the gas limit is either burned or refunded.
+    let gas_cost = result.gas_cost;
+    assert_eq!(gas_cost.message, Some(msg_cid));
+    assert_eq!(
+        gas_cost.gas_used,
+        TokenAmount::from_atto(receipt.gas_used())
+    );
+    assert_eq!(
+        gas_cost.base_fee_burn,
+        TokenAmount::from_atto(100 * receipt.gas_used())
+    );
+    assert_eq!(
+        gas_cost.miner_tip,
+        TokenAmount::from_atto(10 * transfer.gas_limit)
+    );
+    assert!(gas_cost.miner_penalty.is_zero());
+    assert_eq!(
+        gas_cost.total_cost,
+        &gas_cost.base_fee_burn + &gas_cost.over_estimation_burn + &gas_cost.miner_tip
+    );
+    assert_eq!(
+        &gas_cost.total_cost + &gas_cost.refund,
+        transfer.required_funds()
+    );
+}
+
+#[test]
+fn load_tipset_rejects_malformed_keys() {
+    let (sm, _) = calibnet_genesis();
+    let header = |epoch, miner| {
+        let header = CachingBlockHeader::new(RawBlockHeader {
+            epoch,
+            miner_address: Address::new_id(miner),
+            ticket: Some(Ticket::new(VRFProof::new(miner.to_be_bytes().to_vec()))),
+            ..Default::default()
+        });
+        sm.blockstore().put_cbor_default(&header).unwrap();
+        *header.cid()
+    };
+    let (a, b) = (header(1, 1000), header(1, 1001));
+
+    let tipset = Tipset::load_required(sm.blockstore(), &nunny::vec![a, b].into()).unwrap();
+    assert_eq!(&sm.load_tipset(tipset.key()).unwrap(), &tipset);
+
+    let cids = tipset.cids();
+    let unsorted = nunny::vec![*cids.last(), *cids.first()].into();
+    assert!(sm.load_tipset(&unsorted).is_err());
+
+    let mixed_epochs = nunny::vec![a, header(2, 1002)].into();
+    assert!(sm.load_tipset(&mixed_epochs).is_err());
+
+    let missing = nunny::vec![Cid::default()].into();
+    assert!(sm.load_tipset(&missing).is_err());
+}
+
+/// Draws randomness as specified for the builtin actors' `draw_randomness`:
+/// `blake2b256(pers || blake2b256(rbase) || round || entropy)`, with `pers` and
+/// `round` as big-endian `i64`s.
+fn spec_randomness(rbase: &[u8], pers: i64, round: ChainEpoch, entropy: &[u8]) -> String {
+    use crate::utils::encoding::blake2b_256;
+    hex::encode(blake2b_256(
+        &[
+            &pers.to_be_bytes()[..],
+            &blake2b_256(rbase),
+            &round.to_be_bytes(),
+            entropy,
+        ]
+        .concat(),
+    ))
+}
+
+#[test]
+fn randomness_matches_spec() {
+    let (sm, genesis) = calibnet_genesis();
+    let tag = DomainSeparationTag::ElectionProofProduction as i64;
+
+    // The ticket of the calibnet genesis, as produced by Lotus.
+    let ticket = genesis.min_ticket().unwrap().vrfproof.as_bytes().to_vec();
+    assert_eq!(
+        hex::encode(
+            sm.get_chain_randomness(genesis.clone(), tag, 0, b"entropy")
+                .unwrap()
+        ),
+        spec_randomness(&ticket, tag, 0, b"entropy")
+    );
+
+    let epoch = 10;
+    let (_, beacon) = sm.beacon_schedule().beacon_for_epoch(epoch).unwrap();
+    let round = beacon.max_beacon_round_for_epoch(sm.chain_config().network_version(epoch), epoch);
+    let signature = b"beacon".to_vec();
+    let tipset = Arc::new(Tipset::from(CachingBlockHeader::new(RawBlockHeader {
+        epoch,
+        parents: genesis.key().clone(),
+        ticket: genesis.min_ticket().cloned(),
+        beacon_entries: vec![BeaconEntry::new(round, signature.clone())],
+        ..Default::default()
+    })));
+    assert_eq!(
+        hex::encode(
+            sm.get_beacon_randomness(tipset.clone(), tag, epoch, b"entropy")
+                .unwrap()
+        ),
+        spec_randomness(&signature, tag, epoch, b"entropy")
+    );
+    assert!(sm
+        .get_chain_randomness(tipset, tag, epoch + 1, b"entropy")
+        .is_err());
+}
+
+#[test]
+fn locked_funds_sums_market_and_miners() {
+    use fil_actor_power_state::v13::{set_claim, Claim};
+
+    let (sm, _) = calibnet_genesis();
+    let store = sm.blockstore_owned();
+    let mut market = fil_actor_market_state::v13::State::new(&store).unwrap();
+    market.total_client_locked_collateral = TokenAmount::from_whole(1).into();
+    market.total_provider_locked_collateral = TokenAmount::from_whole(2).into();
+    market.total_client_storage_fee = TokenAmount::from_whole(3).into();
+    let mut power = fil_actor_power_state::v13::State::new(&store).unwrap();
+    let mut claims = power.load_claims(&store).unwrap();
+    let mut actors = vec![(
+        Address::MARKET_ACTOR,
+        builtin_actor(sm.blockstore(), BuiltinActor::Market, &market),
+    )];
+    for (id, pledge, deposits, vesting) in [(1000, 10, 20, 30), (1001, 100, 200, 300)] {
+        let miner = Address::new_id(id);
+        let claim = Claim {
+            window_post_proof_type:
+                fvm_shared4::sector::RegisteredPoStProof::StackedDRGWindow32GiBV1P1,
+            raw_byte_power: Default::default(),
+            quality_adj_power: Default::default(),
+        };
+        set_claim(&mut claims, &miner.into(), claim).unwrap();
+        let mut miner_state = miner_state(&sm, &miner_info(id));
+        miner_state.initial_pledge = TokenAmount::from_whole(pledge).into();
+        miner_state.pre_commit_deposits = TokenAmount::from_whole(deposits).into();
+        miner_state.locked_funds = TokenAmount::from_whole(vesting).into();
+        actors.push((
+            miner,
+            builtin_actor(sm.blockstore(), BuiltinActor::Miner, &miner_state),
+        ));
+    }
+    power.save_claims(&mut claims).unwrap();
+    actors.push((
+        Address::POWER_ACTOR,
+        builtin_actor(sm.blockstore(), BuiltinActor::Power, &power),
+    ));
+    let ts = tipset_with_actors(&sm, 0, actors);
+
+    let locked = sm.locked_funds(&ts).unwrap();
+    assert_eq!(
+        locked,
+        LockedFunds {
+            market_escrow: TokenAmount::from_whole(6),
+            initial_pledge: TokenAmount::from_whole(110),
+            precommit_deposits: TokenAmount::from_whole(220),
+            reward_vesting: TokenAmount::from_whole(330),
+        }
+    );
+    assert_eq!(locked.total(), TokenAmount::from_whole(666));
+}
+
+#[test]
+fn vesting_schedule_of_miner_and_multisig() {
+    use crate::shim::clock::EPOCHS_IN_DAY;
+    use fil_actor_miner_state::v13::{VestingFund, VestingFunds};
+    use fvm_shared4::{address::Address as Address4, econ::TokenAmount as TokenAmount4};
+
+    let (sm, _) = calibnet_genesis();
+    let store = sm.blockstore_owned();
+    let mut miner_state = miner_state(&sm, &miner_info(1000));
+    let funds = [(100, 1), (200, 2)]
+        .into_iter()
+        .map(|(epoch, amount)| VestingFund {
+            epoch,
+            amount: TokenAmount::from_whole(amount).into(),
+        })
+        .collect();
+    miner_state.vesting_funds = store.put_cbor_default(&VestingFunds { funds }).unwrap();
+    // Multisig actor types are not a direct dependency, so the v13 `State` is
+    // encoded through its tuple layout.
+    let msig = (
+        vec![Address4::from(Address::new_id(1000))],
+        1_u64,
+        0_i64,
+        TokenAmount4::from(TokenAmount::from_whole(300)),
+        EPOCHS_IN_DAY,
+        3 * EPOCHS_IN_DAY,
+        fil_actors_shared::v13::Map2::<_, i64, ()>::empty(
+            &store,
+            fil_actors_shared::v13::DEFAULT_HAMT_CONFIG,
+            "pending txns",
+        )
+        .flush()
+        .unwrap(),
+    );
+    let (miner, multisig) = (Address::new_id(1001), Address::new_id(1100));
+    let ts = tipset_with_actors(
+        &sm,
+        2 * EPOCHS_IN_DAY,
+        [
+            (
+                miner,
+                builtin_actor(sm.blockstore(), BuiltinActor::Miner, &miner_state),
+            ),
+            (
+                multisig,
+                builtin_actor(sm.blockstore(), BuiltinActor::Multisig, &msig),
+            ),
+        ],
+    );
+
+    let entry = |epoch, amount| VestingEntry {
+        epoch,
+        amount: TokenAmount::from_whole(amount),
+    };
+    assert_eq!(
+        sm.vesting_schedule(&miner, &ts).unwrap(),
+        [entry(100, 1), entry(200, 2)]
+    );
+    assert_eq!(
+        sm.vesting_schedule(&multisig, &ts).unwrap(),
+        [entry(3 * EPOCHS_IN_DAY, 100), entry(4 * EPOCHS_IN_DAY, 100)]
+    );
+    assert!(sm.vesting_schedule(&Address::SYSTEM_ACTOR, &ts).is_err());
+
+    // Before the vesting start, the whole balance is reported from the start.
+    let ts = tipset_with_actors(
+        &sm,
+        EPOCHS_IN_DAY / 2,
+        [(
+            multisig,
+            builtin_actor(sm.blockstore(), BuiltinActor::Multisig, &msig),
+        )],
+    );
+    assert_eq!(
+        sm.vesting_schedule(&multisig, &ts).unwrap(),
+        [
+            entry(2 * EPOCHS_IN_DAY, 100),
+            entry(3 * EPOCHS_IN_DAY, 100),
+            entry(4 * EPOCHS_IN_DAY, 100)
+        ]
+    );
+}
+
+#[tokio::test]
+async fn receipt_and_events_returns_persisted_events() {
+    use fvm_shared4::event::{Entry, Flags, StampedEvent as StampedEvent4};
+
+    let (sm, genesis) = calibnet_genesis();
+    let event = |value| {
+        StampedEvent4::new(
+            1000,
+            vec![Entry {
+                flags: Flags::FLAG_INDEXED_ALL,
+                key: "t1".into(),
+                codec: fvm_ipld_encoding::IPLD_RAW,
+                value,
+            }]
+            .into(),
+        )
+    };
+    let emitted = event(vec![1, 2, 3]);
+    let (msg_cid, _, executed) =
+        chain_with_executed_message(&sm, &genesis, &[StampedEvent::V4(emitted.clone())]);
+    let receipt = Receipt::get_receipt(
+        sm.blockstore(),
+        &executed.min_ticket_block().message_receipts,
+        0,
+    )
+    .unwrap()
+    .unwrap();
+    let Receipt::V4(receipt_v4) = receipt.clone() else {
+        panic!("expected a v4 receipt");
+    };
+    let apply_ret = |events| {
+        ApplyRet::from(fvm4::executor::ApplyRet {
+            msg_receipt: receipt_v4.clone(),
+            penalty: Default::default(),
+            miner_tip: Default::default(),
+            base_fee_burn: Default::default(),
+            over_estimation_burn: Default::default(),
+            refund: Default::default(),
+            gas_refund: 0,
+            gas_burned: 0,
+            failure_info: None,
+            exec_trace: vec![],
+            events,
+        })
+    };
+
+    // The events are not stored until the message is applied with
+    // `persist_events` set.
+    assert!(sm.receipt_and_events(msg_cid).await.is_err());
+
+    // Events that don't match the events root of the receipt are dropped.
+    persist_message_events(sm.blockstore(), &apply_ret(vec![event(vec![4])])).unwrap();
+    assert!(sm.receipt_and_events(msg_cid).await.is_err());
+
+    persist_message_events(sm.blockstore(), &apply_ret(vec![emitted.clone()])).unwrap();
+    let (found, events) = sm.receipt_and_events(msg_cid).await.unwrap();
+    assert_eq!(found, receipt);
+    assert_eq!(events, [StampedEvent::V4(emitted)]);
+}
+
+#[test]
+fn estimate_gas_premium_from_included_messages() {
+    let (sm, genesis) = calibnet_genesis();
+    assert_eq!(
+        sm.estimate_gas_premium(2, 1).unwrap(),
+        TokenAmount::from_atto(2 * MIN_GAS_PREMIUM)
+    );
+    assert_eq!(
+        sm.estimate_gas_premium(6, 3).unwrap(),
+        TokenAmount::from_atto(MIN_GAS_PREMIUM)
+    );
+
+    let message = |sequence, premium, gas_limit| -> Message {
+        let message: Message = fvm_shared3::message::Message {
+            from: Address::new_id(1000).into(),
+            to: Address::new_id(1001).into(),
+            sequence,
+            gas_limit,
+            gas_premium: TokenAmount::from_atto(premium).into(),
+            ..Default::default()
+        }
+        .into();
+        sm.blockstore().put_cbor_default(&message).unwrap();
+        message
+    };
+    let tipset = |epoch, parents: &Tipset, blocks: Vec<Vec<Message>>| {
+        Arc::new(
+            Tipset::new(blocks.into_iter().enumerate().map(|(i, messages)| {
+                let header = CachingBlockHeader::new(RawBlockHeader {
+                    miner_address: Address::new_id(2000 + i as u64),
+                    ticket: Some(Ticket::new(VRFProof::new(vec![i as u8]))),
+                    epoch,
+                    parents: parents.key().clone(),
+                    messages: TipsetValidator::compute_msg_root(sm.blockstore(), &messages, &[])
+                        .unwrap(),
+                    ..Default::default()
+                });
+                sm.blockstore().put_cbor_default(&header).unwrap();
+                header
+            }))
+            .unwrap(),
+        )
+    };
+    let first = tipset(
+        1,
+        &genesis,
+        vec![vec![
+            message(0, 500_000, 3_000_000_000),
+            message(1, 300_000, 1_600_000_000),
+        ]],
+    );
+    let second = tipset(
+        2,
+        &first,
+        vec![
+            vec![message(2, 400_000, 3_000_000_000)],
+            vec![message(3, 200_000, 1_000_000_000)],
+        ],
+    );
+    let head = tipset(3, &second, vec![vec![]]);
+    sm.chain_store().set_heaviest_tipset(head).unwrap();
+
+    // The two sampled tipsets hold three blocks, so one epoch of queue targets
+    // 7.5e9 gas, and 7.875e9 with the 5% offset. The three best paying
+    // messages fit in it; the fourth one sets the price, averaged with the
+    // third. Without the offset the third message would already overflow the
+    // target and the estimate would be 350_000.
+    assert_eq!(
+        sm.estimate_gas_premium(2, 1).unwrap(),
+        TokenAmount::from_atto(250_000)
+    );
+    // Sampling past the first tipset stops at genesis, which adds a block but
+    // no messages: the 10.5e9 gas target fits every message, and the last two
+    // set the price.
+    assert_eq!(
+        sm.estimate_gas_premium(4, 2).unwrap(),
+        TokenAmount::from_atto(250_000)
+    );
+    // The gas target saturates rather than overflowing.
+    assert_eq!(
+        sm.estimate_gas_premium(2, u64::MAX).unwrap(),
+        TokenAmount::from_atto(250_000)
+    );
+}
+
+#[test]
+fn validate_network_name_flags_mismatch() {
+    let (sm, genesis) = calibnet_genesis();
+    assert_eq!(sm.network_name(&genesis).unwrap(), "calibrationnet");
+    sm.validate_network_name(&genesis).unwrap();
+
+    let init_state =
+        fil_actor_init_state::v13::State::new(&sm.blockstore_owned(), "localnet".into()).unwrap();
+    let ts = tipset_with_actors(
+        &sm,
+        1,
+        [(
+            Address::INIT_ACTOR,
+            builtin_actor(sm.blockstore(), BuiltinActor::Init, &init_state),
+        )],
+    );
+    assert_eq!(sm.network_name(&ts).unwrap(), "localnet");
+    assert!(sm.validate_network_name(&ts).is_err());
+
+    // A devnet accepts its own name, but not the name of a builtin network.
+    let devnet = StateManager::new(
+        sm.chain_store().clone(),
+        Arc::new(ChainConfig::devnet()),
+        Arc::new(SyncConfig::default()),
+    )
+    .unwrap();
+    devnet.validate_network_name(&ts).unwrap();
+    assert!(devnet.validate_network_name(&genesis).is_err());
+}
+
+#[test]
+fn init_address_map_lists_every_mapping() {
+    let (sm, _) = calibnet_genesis();
+    let store = sm.blockstore_owned();
+    let addrs = [
+        Address::new_secp256k1(&[1; 65]).unwrap(),
+        Address::new_actor(b"multisig"),
+        Address::new_delegated(10, &[0x22; 20]).unwrap(),
+    ];
+    let mut init_state = fil_actor_init_state::v13::State::new(&store, "mappings".into()).unwrap();
+    for addr in &addrs {
+        init_state
+            .map_addresses_to_id(&store, &addr.into(), None)
+            .unwrap();
+    }
+    let ts = tipset_with_actors(
+        &sm,
+        1,
+        [(
+            Address::INIT_ACTOR,
+            builtin_actor(sm.blockstore(), BuiltinActor::Init, &init_state),
+        )],
+    );
+
+    assert_eq!(
+        sm.init_address_map(&ts).unwrap(),
+        [(addrs[0], 100), (addrs[1], 101), (addrs[2], 102)]
+    );
+}
+
+#[test]
+fn actor_overrides_change_only_the_given_fields() {
+    let (sm, _) = calibnet_genesis();
+    let (account, missing) = (Address::new_id(1000), Address::new_id(1001));
+    let ts = tipset_with_actors(
+        &sm,
+        1,
+        [(
+            account,
+            ActorState::new(Cid::default(), Cid::default(), Zero::zero(), 3, None),
+        )],
+    );
+
+    let balance = TokenAmount::from_whole(10);
+    let overrides = HashMap::from_iter([(
+        account,
+        ActorOverride {
+            balance: Some(balance.clone()),
+            ..Default::default()
+        },
+    )]);
+    let store = Arc::new(MemoryOverlay::new(sm.blockstore_owned()));
+    let state_cid = apply_actor_overrides(&store, ts.parent_state(), &overrides).unwrap();
+    let actor = StateTree::new_from_root(Arc::clone(&store), &state_cid)
+        .unwrap()
+        .get_actor(&account)
+        .unwrap()
+        .unwrap();
+    assert_eq!(TokenAmount::from(actor.balance.clone()), balance);
+    assert_eq!(actor.sequence, 3);
+    // The overridden state is only written to the overlay, and the tipset
+    // state is left untouched.
+    assert!(!sm.blockstore().has(&state_cid).unwrap());
+    let actor = sm.get_required_actor(&account, *ts.parent_state()).unwrap();
+    assert!(actor.balance.is_zero());
+
+    let overrides = HashMap::from_iter([(missing, ActorOverride::default())]);
+    assert!(apply_actor_overrides(&store, ts.parent_state(), &overrides).is_err());
+}
+
+#[tokio::test]
+#[ignore = "downloads the actor bundles"]
+async fn call_with_overrides_leaves_the_blockstore_untouched() {
+    use crate::db::GarbageCollectable as _;
+
+    // Keep the node's store in memory, so that its keys can be listed.
+    let db = Arc::new(MemoryDB::default());
+    crate::utils::db::car_util::load_car(db.as_ref(), networks::calibnet::DEFAULT_GENESIS)
+        .await
+        .unwrap();
+    let (sm, genesis) = calibnet_genesis_on(db.clone(), SyncConfig::default());
+    let (from, to) = (Address::new_id(1000), Address::new_id(1001));
+    let state_root = state_with_bundle(
+        &sm,
+        [(from, TokenAmount::zero()), (to, TokenAmount::zero())],
+    )
+    .await;
+    let ts = Arc::new(Tipset::from(CachingBlockHeader::new(RawBlockHeader {
+        epoch: sm.chain_config().epoch(networks::Height::DragonFix) + 10,
+        parents: genesis.key().clone(),
+        state_root,
+        messages: TipsetValidator::compute_msg_root(sm.blockstore(), &[], &[]).unwrap(),
+        timestamp: genesis.min_timestamp(),
+        ..Default::default()
+    })));
+    let transfer = Message::transfer(from, to, TokenAmount::from_whole(1));
+
+    let result = sm.call(&transfer, Some(ts.clone())).unwrap();
+    assert_eq!(
+        result.msg_rct.unwrap().exit_code().value(),
+        fvm_shared4::error::ExitCode::SYS_INSUFFICIENT_FUNDS.value()
+    );
+
+    let keys = db.get_keys().unwrap();
+    let overrides = HashMap::from_iter([(
+        from,
+        ActorOverride {
+            balance: Some(TokenAmount::from_whole(10)),
+            ..Default::default()
+        },
+    )]);
+    let result = sm
+        .call_with_overrides(&transfer, Some(ts.clone()), &overrides)
+        .unwrap();
+    assert!(result.msg_rct.unwrap().exit_code().is_success());
+    // Neither the overridden state nor the state written by the transfer
+    // reach the node's store.
+    assert_eq!(db.get_keys().unwrap(), keys);
+    let receiver = sm.get_required_actor(&to, *ts.parent_state()).unwrap();
+    assert!(receiver.balance.is_zero());
+}
+
+#[test]
+fn sector_expirations_groups_sectors_by_epoch() {
+    let (sm, _) = calibnet_genesis();
+    let store = sm.blockstore_owned();
+    let mut miner_state = miner_state(&sm, &miner_info(1000));
+    let sectors = [(1, 500), (2, 100), (3, 500)].map(|(sector_number, expiration)| {
+        fil_actor_miner_state::v13::SectorOnChainInfo {
+            sector_number,
+            expiration,
+            ..Default::default()
+        }
+    });
+    miner_state.put_sectors(&store, sectors.into()).unwrap();
+    let miner = Address::new_id(1001);
+    let ts = tipset_with_actors(
+        &sm,
+        0,
+        [(
+            miner,
+            builtin_actor(sm.blockstore(), BuiltinActor::Miner, &miner_state),
+        )],
+    );
+
+    assert_eq!(
+        sm.sector_expirations(&miner, &ts).unwrap(),
+        BTreeMap::from([(100, vec![2]), (500, vec![1, 3])])
+    );
+}
+
+#[test]
+fn genesis_is_loaded_once() {
+    let (sm, genesis) = calibnet_genesis();
+    let first = sm.genesis().unwrap();
+    assert_eq!(*first, *genesis);
+    assert!(Arc::ptr_eq(&first, &sm.genesis().unwrap()));
+
+    // A chain store whose genesis header was never persisted.
+    let header = CachingBlockHeader::new(RawBlockHeader {
+        timestamp: 1,
+        ..Default::default()
+    });
+    let cs = ChainStore::new(
+        Arc::new(MemoryDB::default()),
+        Arc::new(MemoryDB::default()),
+        Arc::new(MemoryDB::default()),
+        Arc::new(ChainConfig::calibnet()),
+        header,
+    )
+    .unwrap();
+    let sm = StateManager::new(
+        Arc::new(cs),
+        Arc::new(ChainConfig::calibnet()),
+        Arc::new(SyncConfig::default()),
+    )
+    .unwrap();
+    assert!(sm.genesis().is_err());
+}
+
+#[tokio::test]
+async fn beacon_entry_for_epoch_is_cached() {
+    use crate::beacon::{
+        mock_beacon::MockBeacon, Beacon, BeaconEntry, BeaconPoint, BeaconSchedule, DrandNetwork,
+    };
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    struct CountingBeacon(Arc<AtomicUsize>, MockBeacon);
+
+    #[async_trait::async_trait]
+    impl Beacon for CountingBeacon {
+        fn network(&self) -> DrandNetwork {
+            self.1.network()
+        }
+
+        fn verify_entries(
+            &self,
+            entries: &[BeaconEntry],
+            prev: &BeaconEntry,
+        ) -> anyhow::Result<bool> {
+            self.1.verify_entries(entries, prev)
+        }
+
+        async fn entry(&self, round: u64) -> anyhow::Result<BeaconEntry> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            self.1.entry(round).await
+        }
+
+        fn max_beacon_round_for_epoch(
+            &self,
+            network_version: NetworkVersion,
+            epoch: ChainEpoch,
+        ) -> u64 {
+            self.1.max_beacon_round_for_epoch(network_version, epoch)
+        }
+    }
+
+    let (mut sm, _) = calibnet_genesis();
+    let fetches = Arc::new(AtomicUsize::new(0));
+    Arc::get_mut(&mut sm).unwrap().beacon = Arc::new(BeaconSchedule(vec![BeaconPoint {
+        height: 0,
+        beacon: Box::new(CountingBeacon(fetches.clone(), MockBeacon::default())),
+    }]));
+
+    let entry = sm.get_beacon_entry_for_epoch(10).await.unwrap();
+    assert_eq!(entry.round(), 10);
+    assert_eq!(sm.get_beacon_entry_for_epoch(10).await.unwrap(), entry);
+    assert_eq!(fetches.load(Ordering::Relaxed), 1);
+
+    sm.get_beacon_entry_for_epoch(11).await.unwrap();
+    assert_eq!(fetches.load(Ordering::Relaxed), 2);
+
+    // Entries verified by block validation are cached too, unless they are
+    // not the entry for the epoch.
+    let entry = BeaconEntry::new(12, b"verified".to_vec());
+    sm.cache_beacon_entry(12, &entry).unwrap();
+    sm.cache_beacon_entry(13, &entry).unwrap();
+    assert_eq!(sm.get_beacon_entry_for_epoch(12).await.unwrap(), entry);
+    assert_eq!(fetches.load(Ordering::Relaxed), 2);
+    assert_eq!(sm.get_beacon_entry_for_epoch(13).await.unwrap().round(), 13);
+    assert_eq!(fetches.load(Ordering::Relaxed), 3);
+}
+
+#[tokio::test]
+async fn state_compute_pool_reports_panics() {
+    let pool = state_compute_pool(1).unwrap();
+    assert_eq!(spawn_on_pool(&pool, || Ok(1)).await.unwrap(), 1);
+    let err = spawn_on_pool::<()>(&pool, || panic!("boom"))
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("panicked: boom"), "{err}");
+    // The pool survives the panic.
+    assert_eq!(spawn_on_pool(&pool, || Ok(2)).await.unwrap(), 2);
+}
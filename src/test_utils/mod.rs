@@ -1,19 +1,35 @@
 // Copyright 2019-2024 ChainSafe Systems
 // SPDX-License-Identifier: Apache-2.0, MIT
 
-use std::str::FromStr;
+use std::{str::FromStr, sync::Arc};
 
 use crate::{
-    blocks::{Ticket, VRFProof},
+    blocks::{Ticket, Tipset, VRFProof},
+    chain::ChainStore,
+    db::MemoryDB,
     eth::EVMMethod,
+    key_management::KeyStore,
     message::SignedMessage,
+    message_pool::{MessagePool, MpoolRpcProvider},
+    networks::{ChainConfig, NetworkChain, ACTOR_BUNDLES_METADATA},
+    rpc::RPCState,
     shim::{
         address::Address,
         crypto::{Signature, SignatureType, SECP_SIG_LEN},
+        machine::{BuiltinActor, BuiltinActorManifest},
         message::{Message, Message_v3},
+        state_tree::ActorState,
+    },
+    state_manager::StateManager,
+    utils::db::{
+        car_stream::{CarBlock, CarHeader},
+        CborStoreExt as _,
     },
 };
 use base64::{prelude::BASE64_STANDARD, Engine};
+use fvm_ipld_blockstore::Blockstore;
+use integer_encoding::VarInt as _;
+use num_traits::Zero;
 
 /// Returns a Ticket to be used for testing
 pub fn construct_ticket() -> Ticket {
@@ -58,6 +74,112 @@ pub fn construct_eth_messages(sequence: u64) -> (Message, SignedMessage) {
     (bls_message, secp_message)
 }
 
+/// Returns an in-memory `CARv1` holding the block headers of `tipsets`, with
+/// the key of the last tipset as its root. Headers are written in order and
+/// only once, so the output is deterministic.
+pub fn build_car(tipsets: &[Tipset]) -> Vec<u8> {
+    let head = tipsets.last().expect("at least one tipset is required");
+    let header = CarHeader {
+        roots: head.key().to_cids(),
+        version: 1,
+    };
+    let header = fvm_ipld_encoding::to_vec(&header).unwrap();
+    let mut car = header.len().encode_var_vec();
+    car.extend(header);
+
+    let mut written = ahash::HashSet::default();
+    for block in tipsets.iter().flat_map(|ts| ts.block_headers()) {
+        if written.insert(*block.cid()) {
+            CarBlock {
+                cid: *block.cid(),
+                data: fvm_ipld_encoding::to_vec(block).unwrap(),
+            }
+            .write(&mut car)
+            .unwrap();
+        }
+    }
+    car
+}
+
+/// Loads the headers of `tipsets` into `db` from a CAR built with
+/// [`build_car`], and returns a chain store over `db` with the default chain
+/// configuration. The first tipset is the genesis and the last one the head.
+pub async fn chain_store_with<DB: Blockstore + Send + Sync + 'static>(
+    db: Arc<DB>,
+    tipsets: &[Tipset],
+) -> Arc<ChainStore<DB>> {
+    crate::utils::db::car_util::load_car(db.as_ref(), build_car(tipsets).as_slice())
+        .await
+        .unwrap();
+    let genesis = tipsets.first().expect("at least one tipset is required");
+    let cs = ChainStore::new(
+        db,
+        Arc::new(MemoryDB::default()),
+        Arc::new(MemoryDB::default()),
+        Arc::new(ChainConfig::default()),
+        genesis.min_ticket_block().clone(),
+    )
+    .unwrap();
+    cs.set_heaviest_tipset(Arc::new(tipsets.last().unwrap().clone()))
+        .unwrap();
+    Arc::new(cs)
+}
+
+/// Returns the manifest of the calibnet actors v13 bundle.
+pub fn calibnet_v13_manifest() -> &'static BuiltinActorManifest {
+    &ACTOR_BUNDLES_METADATA
+        .get(&(NetworkChain::Calibnet, "v13.0.0".into()))
+        .unwrap()
+        .manifest
+}
+
+/// Stores `state` in `db` as the head of a builtin actor of the given type,
+/// using the code CIDs of the calibnet actors v13 bundle.
+pub fn builtin_actor(
+    db: &impl Blockstore,
+    actor_type: BuiltinActor,
+    state: &impl serde::Serialize,
+) -> ActorState {
+    let code = calibnet_v13_manifest().get(actor_type).unwrap();
+    let head = db.put_cbor_default(state).unwrap();
+    ActorState::new(code, head, Zero::zero(), 0, None)
+}
+
+/// Builds an RPC context over `state_manager`, with a message pool that
+/// doesn't publish anywhere.
+pub fn rpc_ctx<DB: Blockstore + Send + Sync + 'static>(
+    state_manager: Arc<StateManager<DB>>,
+    keystore: KeyStore,
+    gc_requests: Option<flume::Sender<crate::db::GcRequest>>,
+) -> Arc<RPCState<DB>> {
+    let (network_send, _) = flume::bounded(5);
+    let mpool = MessagePool::new(
+        MpoolRpcProvider::new(
+            state_manager.chain_store().publisher().clone(),
+            state_manager.clone(),
+        ),
+        "test".into(),
+        network_send.clone(),
+        Default::default(),
+        state_manager.chain_config().clone(),
+        &mut tokio::task::JoinSet::new(),
+    )
+    .unwrap();
+    Arc::new(RPCState {
+        state_manager,
+        keystore: Arc::new(tokio::sync::RwLock::new(keystore)),
+        mpool: Arc::new(mpool),
+        bad_blocks: Default::default(),
+        sync_state: Default::default(),
+        network_send,
+        network_name: "test".into(),
+        start_time: chrono::Utc::now(),
+        shutdown: tokio::sync::mpsc::channel(1).0,
+        tipset_send: flume::bounded(5).0,
+        gc_requests,
+    })
+}
+
 // Serialize macro used for testing
 #[macro_export]
 macro_rules! to_string_with {
@@ -75,3 +197,54 @@ macro_rules! from_str_with {
         $deserializer(&mut serde_json::de::Deserializer::from_str($str)).unwrap()
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::blocks::{CachingBlockHeader, RawBlockHeader, TipsetKey};
+    use crate::chain_sync::SyncConfig;
+    use crate::db::car::PlainCar;
+
+    #[test]
+    fn build_car_round_trips_through_state_manager() {
+        let genesis = CachingBlockHeader::new(RawBlockHeader {
+            timestamp: 1,
+            ..Default::default()
+        });
+        let child = |miner| {
+            CachingBlockHeader::new(RawBlockHeader {
+                parents: TipsetKey::from(nunny::vec![*genesis.cid()]),
+                epoch: 1,
+                miner_address: Address::new_id(miner),
+                ..Default::default()
+            })
+        };
+        let tipsets = [
+            Tipset::from(genesis.clone()),
+            Tipset::new([child(1000), child(1001)]).unwrap(),
+        ];
+
+        let car = build_car(&tipsets);
+        assert_eq!(car, build_car(&tipsets));
+
+        let db = Arc::new(PlainCar::new(car).unwrap());
+        assert_eq!(db.heaviest_tipset().unwrap(), tipsets[1]);
+        let chain_config = Arc::new(ChainConfig::default());
+        let cs = ChainStore::new(
+            db,
+            Arc::new(MemoryDB::default()),
+            Arc::new(MemoryDB::default()),
+            chain_config.clone(),
+            genesis,
+        )
+        .unwrap();
+        let sm =
+            StateManager::new(Arc::new(cs), chain_config, Arc::new(SyncConfig::default())).unwrap();
+        let head = sm
+            .chain_store()
+            .chain_index
+            .load_required_tipset(tipsets[1].key())
+            .unwrap();
+        assert_eq!(*head, tipsets[1]);
+    }
+}
@@ -1679,6 +1679,7 @@ async fn start_offline_server(
         start_time: chrono::Utc::now(),
         shutdown,
         tipset_send,
+        gc_requests: None,
     };
     rpc_state.sync_state.write().set_stage(SyncStage::Idle);
     start_offline_rpc(rpc_state, rpc_port, shutdown_recv).await?;